@@ -0,0 +1,16 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.8" tiledversion="1.8.2" name="basic" tilewidth="32" tileheight="32" tilecount="3" columns="0">
+ <grid orientation="orthogonal" width="1" height="1"/>
+ <tile id="0" type="wall">
+  <image width="32" height="32" source="../tilesets/basic/wall.png"/>
+ </tile>
+ <tile id="1">
+  <properties>
+   <property name="tile" value="block"/>
+  </properties>
+  <image width="32" height="32" source="../tilesets/basic/breakable.png"/>
+ </tile>
+ <tile id="2" type="coin_block">
+  <image width="32" height="32" source="../tilesets/basic/breakable.png"/>
+ </tile>
+</tileset>
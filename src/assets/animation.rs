@@ -0,0 +1,467 @@
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead};
+use image::ImageError;
+use assets::atlas::AtlasRegion;
+use assets::cache::TextureCache;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlayMode {
+    Loop,
+    // Plays forwards and then backwards, without repeating the end frames
+    PingPong,
+    // Stops on the last frame
+    Once,
+}
+
+impl PlayMode {
+    pub fn parse(name: &str) -> Option<PlayMode> {
+        match name {
+            "loop" => Some(PlayMode::Loop),
+            "ping_pong" => Some(PlayMode::PingPong),
+            "once" => Some(PlayMode::Once),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClipFrame {
+    // Index into the AnimationSet's frames
+    pub frame: u32,
+    // Seconds
+    pub duration: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clip {
+    pub name: String,
+    pub mode: PlayMode,
+    pub frames: Vec<ClipFrame>,
+}
+
+impl Clip {
+    pub fn new(name: &str, mode: PlayMode) -> Clip {
+        Clip {
+            name: name.into(),
+            mode: mode,
+            frames: Vec::new(),
+        }
+    }
+    
+    // How long it takes to get back to the first frame, or to reach the end
+    // of a clip that plays once
+    pub fn period(&self) -> f64 {
+        (0..self.steps()).map(|i| self.step(i).duration).fold(0.0, |a, b| a + b)
+    }
+    
+    // The frame showing at a time since the clip started, which should be
+    // within the period unless the clip plays once
+    pub fn frame_at(&self, time: f64) -> u32 {
+        let mut time = time;
+        let steps = self.steps();
+        for i in 0..steps {
+            let step = self.step(i);
+            if time < step.duration {
+                return step.frame;
+            }
+            time -= step.duration;
+        }
+        self.step(steps - 1).frame
+    }
+    
+    pub fn finished(&self, time: f64) -> bool {
+        self.mode == PlayMode::Once && time >= self.period()
+    }
+    
+    // Frames shown over one period, counting the way back of a ping-pong
+    fn steps(&self) -> usize {
+        let len = self.frames.len();
+        if self.mode == PlayMode::PingPong && len > 2 {
+            len * 2 - 2
+        } else {
+            len
+        }
+    }
+    
+    fn step(&self, i: usize) -> &ClipFrame {
+        let len = self.frames.len();
+        if i < len {
+            &self.frames[i]
+        } else {
+            &self.frames[len * 2 - 2 - i]
+        }
+    }
+}
+
+// The frames of one or more sprite sheets and the named clips that show them
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationSet {
+    frames: Vec<AtlasRegion>,
+    clips: Vec<Clip>,
+}
+
+impl AnimationSet {
+    pub fn new() -> AnimationSet {
+        AnimationSet {
+            frames: Vec::new(),
+            clips: Vec::new(),
+        }
+    }
+    
+    // One looping clip showing each of the frames in turn for the same time
+    pub fn uniform(frames: Vec<AtlasRegion>, name: &str, length: f64) -> AnimationSet {
+        let mut clip = Clip::new(name, PlayMode::Loop);
+        let duration = length / frames.len() as f64;
+        clip.frames = (0..frames.len()).map(|frame| ClipFrame {
+            frame: frame as u32,
+            duration: duration,
+        }).collect();
+        
+        AnimationSet {
+            frames: frames,
+            clips: vec![clip],
+        }
+    }
+    
+    pub fn frames(&self) -> &[AtlasRegion] {
+        &self.frames
+    }
+    
+    pub fn clips(&self) -> &[Clip] {
+        &self.clips
+    }
+    
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|clip| clip.name == name)
+    }
+    
+    // Cuts a sheet into a grid of frames, row by row from the top left. Any
+    // space left over on the right or bottom is ignored.
+    pub fn add_sheet(&mut self, sheet: AtlasRegion, frame_width: u32, frame_height: u32) {
+        for row in 0..sheet.height / frame_height {
+            for col in 0..sheet.width / frame_width {
+                self.frames.push(sheet.sub_region(
+                    col * frame_width, row * frame_height, frame_width, frame_height
+                ));
+            }
+        }
+    }
+    
+    pub fn add_clip(&mut self, clip: Clip) {
+        self.clips.push(clip);
+    }
+    
+    // A file looks like
+    //
+    //     sheet assets/textures/player.png 32 32
+    //     clip idle loop 0:0.5 1:0.5
+    //     clip jump once 4:0.1 5:0.2
+    //
+    // where sheets are cut into frames of the given size, numbered across
+    // every sheet in the order they're listed. Clips list frame:seconds pairs
+    // and play as loop, ping_pong or once. Lines starting with `#` are
+    // comments.
+    pub fn parse<R: BufRead>(reader: R, textures: &mut TextureCache) -> Res<AnimationSet> {
+        let mut set = AnimationSet::new();
+        
+        for (i, line) in reader.lines().enumerate() {
+            let line = try!(line);
+            let line_num = i as u32 + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            
+            let mut split = line.split_whitespace();
+            match split.next().unwrap() {
+                "sheet" => {
+                    let path = try!(split.next().ok_or(Error::BadLine(line_num)));
+                    let width = try!(split.next().and_then(|w| w.parse().ok()).ok_or(Error::BadLine(line_num)));
+                    let height = try!(split.next().and_then(|h| h.parse().ok()).ok_or(Error::BadLine(line_num)));
+                    let sheet = try!(textures.region(path));
+                    if width == 0 || height == 0 || width > sheet.width || height > sheet.height {
+                        return Err(Error::BadFrameSize(line_num));
+                    }
+                    set.add_sheet(sheet, width, height);
+                },
+                "clip" => {
+                    let name = try!(split.next().ok_or(Error::BadLine(line_num)));
+                    let mode = try!(split.next().ok_or(Error::BadLine(line_num)));
+                    let mode = try!(PlayMode::parse(mode).ok_or_else(|| {
+                        Error::UnknownMode(line_num, mode.into())
+                    }));
+                    if set.find(name).is_some() {
+                        return Err(Error::DuplicateClip(line_num, name.into()));
+                    }
+                    
+                    let mut clip = Clip::new(name, mode);
+                    for frame in split {
+                        let frame = try!(parse_frame(frame).ok_or(Error::BadLine(line_num)));
+                        if frame.frame as usize >= set.frames.len() {
+                            return Err(Error::UnknownFrame(line_num, frame.frame));
+                        }
+                        clip.frames.push(frame);
+                    }
+                    if clip.frames.is_empty() {
+                        return Err(Error::BadLine(line_num));
+                    }
+                    set.add_clip(clip);
+                },
+                _ => return Err(Error::BadLine(line_num)),
+            }
+        }
+        
+        if set.clips.is_empty() {
+            return Err(Error::NoClips);
+        }
+        Ok(set)
+    }
+    
+    pub fn load(path: &str, textures: &mut TextureCache) -> Res<AnimationSet> {
+        use std::fs::File;
+        use std::io::BufReader;
+        let file = try!(File::open(path).map_err(|e| Error::in_file(path, Error::Io(e))));
+        AnimationSet::parse(BufReader::new(file), textures).map_err(|e| Error::in_file(path, e))
+    }
+}
+
+// `frame:seconds`, where the time has to be above zero
+fn parse_frame(s: &str) -> Option<ClipFrame> {
+    let mut split = s.splitn(2, ':');
+    let frame = split.next().and_then(|f| f.parse().ok());
+    let duration = split.next().and_then(|d| d.parse().ok());
+    match (frame, duration) {
+        (Some(frame), Some(duration)) if duration > 0.0 => Some(ClipFrame {
+            frame: frame,
+            duration: duration,
+        }),
+        _ => None,
+    }
+}
+
+pub type Res<T> = Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    BadLine(u32),
+    BadFrameSize(u32),
+    UnknownMode(u32, String),
+    UnknownFrame(u32, u32),
+    DuplicateClip(u32, String),
+    NoClips,
+    Image(ImageError),
+    Io(io::Error),
+    InFile { path: String, error: Box<Error> },
+}
+
+impl Error {
+    pub fn in_file(path: &str, error: Error) -> Error {
+        Error::InFile {
+            path: path.into(),
+            error: Box::new(error),
+        }
+    }
+    
+    pub fn line(&self) -> Option<u32> {
+        match *self {
+            Error::BadLine(line) |
+            Error::BadFrameSize(line) |
+            Error::UnknownMode(line, _) |
+            Error::UnknownFrame(line, _) |
+            Error::DuplicateClip(line, _) => Some(line),
+            Error::InFile { ref error, .. } => error.line(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::BadLine(line) => write!(f, "{} bad animation line", line),
+            Error::BadFrameSize(line) => write!(f, "{} frame size doesn't fit the sheet", line),
+            Error::UnknownMode(line, ref mode) => write!(f, "{} unknown play mode '{}'", line, mode),
+            Error::UnknownFrame(line, frame) => write!(f, "{} there's no frame {}", line, frame),
+            Error::DuplicateClip(line, ref name) => {
+                write!(f, "{} clip {} is already defined", line, name)
+            },
+            Error::NoClips => write!(f, "no clips defined"),
+            Error::Image(ref e) => write!(f, "{}", e),
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::InFile { ref path, ref error } => {
+                if error.line().is_some() {
+                    write!(f, "{}:{}", path, error)
+                } else {
+                    write!(f, "{}: {}", path, error)
+                }
+            },
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::BadLine(_) => "bad animation line",
+            Error::BadFrameSize(_) => "frame size doesn't fit the sheet",
+            Error::UnknownMode(..) => "unknown play mode",
+            Error::UnknownFrame(..) => "unknown frame",
+            Error::DuplicateClip(..) => "clip is already defined",
+            Error::NoClips => "no clips defined",
+            Error::Image(ref e) => error::Error::description(e),
+            Error::Io(ref e) => error::Error::description(e),
+            Error::InFile { error: ref inner, .. } => error::Error::description(&**inner),
+        }
+    }
+    
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Image(ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
+            Error::InFile { ref error, .. } => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(io: io::Error) -> Error {
+        Error::Io(io)
+    }
+}
+
+impl From<ImageError> for Error {
+    fn from(image: ImageError) -> Error {
+        Error::Image(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnimationSet, Clip, ClipFrame, PlayMode, Error, Res};
+    use assets::cache::TextureCache;
+    
+    fn clip(mode: PlayMode, durations: &[f64]) -> Clip {
+        let mut clip = Clip::new("test", mode);
+        clip.frames = durations.iter().enumerate().map(|(frame, &duration)| ClipFrame {
+            frame: frame as u32,
+            duration: duration,
+        }).collect();
+        clip
+    }
+    
+    // The player sheet is 4 by 2 frames of 32 pixels
+    fn parse(text: &str) -> Res<AnimationSet> {
+        let text = format!("sheet assets/textures/player.png 32 32\n{}", text);
+        AnimationSet::parse(text.as_bytes(), &mut TextureCache::new(None))
+    }
+    
+    #[test]
+    fn ping_pong() {
+        let clip = clip(PlayMode::PingPong, &[1.0, 1.0, 1.0]);
+        assert_eq!(clip.period(), 4.0);
+        let frames: Vec<_> = [0.5, 1.5, 2.5, 3.5].iter().map(|&t| clip.frame_at(t)).collect();
+        assert_eq!(frames, vec![0, 1, 2, 1]);
+        
+        // With two frames there's nothing in between to go back through
+        let clip = self::clip(PlayMode::PingPong, &[1.0, 2.0]);
+        assert_eq!(clip.period(), 3.0);
+        assert_eq!(clip.frame_at(2.5), 1);
+    }
+    
+    #[test]
+    fn once_clamps() {
+        let clip = clip(PlayMode::Once, &[0.1, 0.2]);
+        assert_eq!(clip.frame_at(0.05), 0);
+        assert_eq!(clip.frame_at(0.15), 1);
+        assert_eq!(clip.frame_at(10.0), 1);
+        assert!(!clip.finished(0.2));
+        assert!(clip.finished(0.3));
+        assert!(clip.finished(10.0));
+        
+        assert!(!self::clip(PlayMode::Loop, &[0.1, 0.2]).finished(10.0));
+    }
+    
+    #[test]
+    fn parse_file() {
+        let mut textures = TextureCache::new(None);
+        let text = "sheet assets/textures/player.png 32 32\n\
+                    # comment\n\
+                    \n\
+                    clip run loop 2:0.1 3:0.1\n\
+                    clip hurt once 7:0.3\n";
+        let set = AnimationSet::parse(text.as_bytes(), &mut textures).unwrap();
+        let sheet = textures.region("assets/textures/player.png").unwrap();
+        assert_eq!(set.frames().len(), 8);
+        assert_eq!(set.frames()[5], sheet.sub_region(32, 32, 32, 32));
+        assert_eq!(set.find("hurt"), Some(1));
+        let run = &set.clips()[0];
+        assert_eq!(run.mode, PlayMode::Loop);
+        assert_eq!(run.frames, vec![
+            ClipFrame { frame: 2, duration: 0.1 },
+            ClipFrame { frame: 3, duration: 0.1 },
+        ]);
+    }
+    
+    #[test]
+    fn parse_errors() {
+        match parse("frobnicate\n") {
+            Err(Error::BadLine(2)) => {},
+            other => panic!("expected a bad line, got {:?}", other),
+        }
+        match parse("clip idle loop 0:0\n") {
+            Err(Error::BadLine(2)) => {},
+            other => panic!("expected a bad frame, got {:?}", other),
+        }
+        match parse("clip idle loop\n") {
+            Err(Error::BadLine(2)) => {},
+            other => panic!("expected a clip without frames to fail, got {:?}", other),
+        }
+        match parse("clip idle backwards 0:1\n") {
+            Err(Error::UnknownMode(2, ref mode)) if mode == "backwards" => {},
+            other => panic!("expected an unknown mode, got {:?}", other),
+        }
+        match parse("clip idle loop 8:1\n") {
+            Err(Error::UnknownFrame(2, 8)) => {},
+            other => panic!("expected an unknown frame, got {:?}", other),
+        }
+        match parse("clip idle loop 0:1\nclip idle once 1:1\n") {
+            Err(Error::DuplicateClip(3, ref name)) if name == "idle" => {},
+            other => panic!("expected a duplicate clip, got {:?}", other),
+        }
+        match parse("sheet assets/textures/player.png 256 32\nclip idle loop 0:1\n") {
+            Err(Error::BadFrameSize(2)) => {},
+            other => panic!("expected a bad frame size, got {:?}", other),
+        }
+        match parse("") {
+            Err(Error::NoClips) => {},
+            other => panic!("expected no clips, got {:?}", other),
+        }
+        match parse("sheet assets/textures/missing.png 32 32\n") {
+            Err(Error::Image(_)) => {},
+            other => panic!("expected a missing image, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn errors_name_the_file() {
+        let bad_line = Error::in_file("assets/animations/player.cfg", Error::BadLine(3));
+        assert_eq!(bad_line.line(), Some(3));
+        assert_eq!(bad_line.to_string(), "assets/animations/player.cfg:3 bad animation line");
+        
+        let no_clips = Error::in_file("assets/animations/player.cfg", Error::NoClips);
+        assert_eq!(no_clips.to_string(), "assets/animations/player.cfg: no clips defined");
+        
+        match AnimationSet::load("assets/animations/missing.cfg", &mut TextureCache::new(None)) {
+            Err(Error::InFile { ref path, ref error }) => {
+                assert_eq!(path, "assets/animations/missing.cfg");
+                match **error {
+                    Error::Io(_) => {},
+                    ref other => panic!("expected an IO error, got {:?}", other),
+                }
+            },
+            other => panic!("expected an error naming the file, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::sync::Arc;
+use image::{ImageBuffer, Rgba};
+use glium;
+use glium::texture::Texture2dArray;
+use glium::backend::glutin_backend::GlutinFacade;
+
+pub type RgbaImage = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+// Where an image was placed, in pixels
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PackedRect {
+    pub page: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// A row of images as tall as the tallest one put in it
+struct Shelf {
+    y: u32,
+    height: u32,
+    // Where the next image in the row goes
+    x: u32,
+}
+
+struct Page {
+    shelves: Vec<Shelf>,
+    // Where the next shelf goes
+    next_y: u32,
+}
+
+// Packs rectangles into square pages a shelf at a time. Nothing is ever
+// moved once it's placed.
+pub struct Packer {
+    size: u32,
+    // Empty pixels left around every rectangle so filtering doesn't pick up
+    // its neighbours
+    padding: u32,
+    pages: Vec<Page>,
+}
+
+impl Packer {
+    pub fn new(size: u32, padding: u32) -> Packer {
+        Packer {
+            size: size,
+            padding: padding,
+            pages: Vec::new(),
+        }
+    }
+    
+    pub fn page_size(&self) -> u32 {
+        self.size
+    }
+    
+    pub fn page_count(&self) -> u32 {
+        self.pages.len() as u32
+    }
+    
+    // None if the rectangle doesn't fit on a page at all
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<PackedRect> {
+        let padded_width = width + self.padding * 2;
+        let padded_height = height + self.padding * 2;
+        if padded_width > self.size || padded_height > self.size {
+            return None;
+        }
+        
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            if let Some((x, y)) = Packer::place(page, self.size, padded_width, padded_height) {
+                return Some(PackedRect {
+                    page: i as u32,
+                    x: x + self.padding,
+                    y: y + self.padding,
+                    width: width,
+                    height: height,
+                });
+            }
+        }
+        
+        let mut page = Page {
+            shelves: Vec::new(),
+            next_y: 0,
+        };
+        let (x, y) = Packer::place(&mut page, self.size, padded_width, padded_height).unwrap();
+        self.pages.push(page);
+        Some(PackedRect {
+            page: self.pages.len() as u32 - 1,
+            x: x + self.padding,
+            y: y + self.padding,
+            width: width,
+            height: height,
+        })
+    }
+    
+    // Puts a rectangle on the first shelf with room for it, or on a new shelf
+    fn place(page: &mut Page, size: u32, width: u32, height: u32) -> Option<(u32, u32)> {
+        for shelf in &mut page.shelves {
+            if height <= shelf.height && shelf.x + width <= size {
+                let x = shelf.x;
+                shelf.x += width;
+                return Some((x, shelf.y));
+            }
+        }
+        
+        if page.next_y + height > size {
+            return None;
+        }
+        let y = page.next_y;
+        page.next_y += height;
+        page.shelves.push(Shelf {
+            y: y,
+            height: height,
+            x: width,
+        });
+        Some((0, y))
+    }
+}
+
+// Part of an atlas page. `uv` is [left, top, right, bottom] in texture
+// coordinates, with rows counted from the top of the image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AtlasRegion {
+    pub page: u32,
+    pub uv: [f32; 4],
+    // Size in pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AtlasRegion {
+    // A rectangle of this region, in pixels from its top left corner
+    pub fn sub_region(&self, x: u32, y: u32, width: u32, height: u32) -> AtlasRegion {
+        let u = (self.uv[2] - self.uv[0]) / self.width as f32;
+        let v = (self.uv[3] - self.uv[1]) / self.height as f32;
+        AtlasRegion {
+            page: self.page,
+            uv: [
+                self.uv[0] + x as f32 * u,
+                self.uv[1] + y as f32 * v,
+                self.uv[0] + (x + width) as f32 * u,
+                self.uv[1] + (y + height) as f32 * v,
+            ],
+            width: width,
+            height: height,
+        }
+    }
+}
+
+// Images of any size packed into pages of one texture array
+pub struct Atlas {
+    packer: Packer,
+    pages: Vec<RgbaImage>,
+    texture: Option<Arc<Texture2dArray>>,
+    // Set when the pages changed since the texture was uploaded
+    dirty: bool,
+}
+
+impl Atlas {
+    pub fn new(page_size: u32) -> Atlas {
+        Atlas {
+            packer: Packer::new(page_size, 1),
+            pages: Vec::new(),
+            texture: None,
+            dirty: false,
+        }
+    }
+    
+    pub fn page_count(&self) -> u32 {
+        self.packer.page_count()
+    }
+    
+    // Makes room for an image without storing any pixels, for when nothing is
+    // ever uploaded. None if it's bigger than a page.
+    pub fn reserve(&mut self, width: u32, height: u32) -> Option<AtlasRegion> {
+        let rect = self.packer.pack(width, height);
+        rect.map(|rect| self.region(rect))
+    }
+    
+    // None if the image is bigger than a page
+    pub fn add(&mut self, image: &RgbaImage) -> Option<AtlasRegion> {
+        let (width, height) = image.dimensions();
+        let rect = match self.packer.pack(width, height) {
+            Some(rect) => rect,
+            None => return None,
+        };
+        
+        let size = self.packer.page_size();
+        while self.pages.len() as u32 <= rect.page {
+            self.pages.push(ImageBuffer::new(size, size));
+        }
+        let page = &mut self.pages[rect.page as usize];
+        for y in 0..height {
+            for x in 0..width {
+                page.put_pixel(rect.x + x, rect.y + y, *image.get_pixel(x, y));
+            }
+        }
+        self.dirty = true;
+        
+        Some(self.region(rect))
+    }
+    
+    fn region(&self, rect: PackedRect) -> AtlasRegion {
+        let size = self.packer.page_size() as f32;
+        AtlasRegion {
+            page: rect.page,
+            uv: [
+                rect.x as f32 / size,
+                rect.y as f32 / size,
+                (rect.x + rect.width) as f32 / size,
+                (rect.y + rect.height) as f32 / size,
+            ],
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+    
+    // Uploads the pages if anything was added since the last call. Regions
+    // only ever get added, so textures returned earlier stay valid for the
+    // regions that existed back then.
+    pub fn texture(&mut self, display: &GlutinFacade) -> Option<Arc<Texture2dArray>> {
+        if self.dirty || self.texture.is_none() {
+            self.dirty = false;
+            if self.pages.is_empty() {
+                return None;
+            }
+            
+            let size = self.packer.page_size();
+            let images = self.pages.iter().map(|page| {
+                glium::texture::RawImage2d::from_raw_rgba(page.clone().into_raw(), (size, size))
+            }).collect();
+            self.texture = Some(Arc::new(Texture2dArray::new(display, images).unwrap()));
+        }
+        self.texture.clone()
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use image::{self, GenericImage, ImageDecoder, ImageError, ImageResult};
+use glium::texture::Texture2dArray;
+use glium::backend::glutin_backend::GlutinFacade;
+use assets::atlas::{Atlas, AtlasRegion};
+
+const PAGE_SIZE: u32 = 1024;
+
+// Loads each image once and packs it into a shared atlas
+pub struct TextureCache {
+    // None when running headless, in which case there's never a texture and
+    // images are only measured to give them a place in the atlas
+    display: Option<GlutinFacade>,
+    atlas: Atlas,
+    regions: HashMap<PathBuf, AtlasRegion>,
+}
+
+impl TextureCache {
+    pub fn new(display: Option<&GlutinFacade>) -> TextureCache {
+        TextureCache {
+            display: display.cloned(),
+            atlas: Atlas::new(PAGE_SIZE),
+            regions: HashMap::new(),
+        }
+    }
+    
+    // Where an image ended up in the atlas, loading it the first time
+    pub fn region<P: AsRef<Path>>(&mut self, path: P) -> ImageResult<AtlasRegion> {
+        let path = path.as_ref();
+        if let Some(&region) = self.regions.get(path) {
+            return Ok(region);
+        }
+        
+        let region = if self.display.is_some() {
+            let image = try!(image::open(path)).to_rgba();
+            self.atlas.add(&image)
+        } else {
+            let (width, height) = try!(dimensions(path));
+            self.atlas.reserve(width, height)
+        };
+        let region = match region {
+            Some(region) => region,
+            None => return Err(ImageError::DimensionError),
+        };
+        self.regions.insert(path.to_path_buf(), region);
+        Ok(region)
+    }
+    
+    // The atlas with every image loaded so far
+    pub fn texture(&mut self) -> Option<Arc<Texture2dArray>> {
+        match self.display {
+            Some(ref display) => self.atlas.texture(display),
+            None => None,
+        }
+    }
+}
+
+// Reads just the header of PNGs. Anything else is rare enough to decode.
+fn dimensions(path: &Path) -> ImageResult<(u32, u32)> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("png") | Some("PNG") => {
+            let file = try!(File::open(path));
+            image::png::PNGDecoder::new(BufReader::new(file)).dimensions()
+        },
+        _ => Ok(try!(image::open(path)).dimensions()),
+    }
+}
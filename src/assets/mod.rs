@@ -0,0 +1,7 @@
+pub use self::animation::{AnimationSet, Clip, ClipFrame, PlayMode};
+pub use self::atlas::{Atlas, AtlasRegion, PackedRect, Packer};
+pub use self::cache::TextureCache;
+
+pub mod animation;
+pub mod atlas;
+pub mod cache;
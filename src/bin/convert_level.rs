@@ -0,0 +1,49 @@
+extern crate ecs_game;
+
+use std::env;
+use std::process;
+use ecs_game::world::binary;
+use ecs_game::world::tilemap::{self, load_map};
+use ecs_game::world::tileset::Tileset;
+
+// Converts text levels to the binary format and checks that the result loads
+// back into exactly the same map.
+//
+//     convert_level <level.txt> <level.lvl> [tiles.cfg]
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 || args.len() > 3 {
+        println!("Usage: convert_level <level.txt> <level.lvl> [tiles.cfg]");
+        process::exit(2);
+    }
+    let input = &args[0];
+    let output = &args[1];
+    let tileset_path = args.get(2).map(|path| &path[..]).unwrap_or("assets/tilesets/basic/tiles.cfg");
+    
+    let tileset = match Tileset::load(tileset_path) {
+        Ok(tileset) => tileset,
+        Err(e) => fail(&format!("Couldn't load tileset: {}", tilemap::Error::in_file(tileset_path, From::from(e)))),
+    };
+    let tilemap = match load_map(input, &tileset) {
+        Ok(tilemap) => tilemap,
+        Err(e) => fail(&format!("Couldn't load level: {}", e)),
+    };
+    
+    let mut bytes = Vec::new();
+    binary::save(&tilemap, &mut bytes).unwrap();
+    match binary::load(&mut &bytes[..], &tileset) {
+        Ok(ref reloaded) if *reloaded == tilemap => {},
+        Ok(_) => fail("Converted level doesn't match the original"),
+        Err(e) => fail(&format!("Converted level doesn't load: {}", e)),
+    }
+    
+    if let Err(e) = binary::save_file(&tilemap, output) {
+        fail(&format!("Couldn't save level: {}", e));
+    }
+    println!("Wrote {} ({} bytes)", output, bytes.len());
+}
+
+fn fail(message: &str) -> ! {
+    println!("{}", message);
+    process::exit(1);
+}
@@ -0,0 +1,41 @@
+use cgmath::{Point2, Vector2};
+use world::aabb::Aabb;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Collider {
+    pub half_size: Vector2<f32>,
+    
+    // Contacts from the last collision step
+    pub grounded: bool,
+    pub ceiling: bool,
+    pub wall_left: bool,
+    pub wall_right: bool,
+    // (row, col) of the tile that was hit from below
+    pub ceiling_tile: Option<(u32, u32)>,
+}
+
+impl Collider {
+    pub fn new(width: f32, height: f32) -> Collider {
+        Collider {
+            half_size: Vector2::new(width / 2.0, height / 2.0),
+            
+            grounded: false,
+            ceiling: false,
+            wall_left: false,
+            wall_right: false,
+            ceiling_tile: None,
+        }
+    }
+    
+    pub fn aabb(&self, position: Point2<f32>) -> Aabb {
+        Aabb::new(position, self.half_size)
+    }
+    
+    pub fn clear_contacts(&mut self) {
+        self.grounded = false;
+        self.ceiling = false;
+        self.wall_left = false;
+        self.wall_right = false;
+        self.ceiling_tile = None;
+    }
+}
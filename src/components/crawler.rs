@@ -0,0 +1,143 @@
+use std::io::{self, BufRead};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Crawler {
+    // Patrol speed in tiles per second
+    pub speed: f32,
+    // -1.0 for left, 1.0 for right
+    pub direction: f32,
+    pub turn_at_walls: bool,
+    pub turn_at_ledges: bool,
+    
+    // Damage dealt to the player on side contact
+    pub damage: u32,
+    // Whether landing on top of it defeats it
+    pub stompable: bool,
+    // Stomped, waiting for the entity to be removed
+    pub defeated: bool,
+}
+
+impl Crawler {
+    pub fn new() -> Crawler {
+        Crawler {
+            speed: 2.0,
+            direction: -1.0,
+            turn_at_walls: true,
+            turn_at_ledges: true,
+            
+            damage: 1,
+            stompable: true,
+            defeated: false,
+        }
+    }
+    
+    // Changes a setting by the name crawler files use for it
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), SettingError> {
+        match key {
+            "speed" => self.speed = try!(parse_value(value)),
+            "direction" => self.direction = match value {
+                "left" => -1.0,
+                "right" => 1.0,
+                _ => return Err(SettingError::BadValue),
+            },
+            "turn_at_walls" => self.turn_at_walls = try!(parse_value(value)),
+            "turn_at_ledges" => self.turn_at_ledges = try!(parse_value(value)),
+            "damage" => self.damage = try!(parse_value(value)),
+            "stompable" => self.stompable = try!(parse_value(value)),
+            _ => return Err(SettingError::Unknown),
+        }
+        Ok(())
+    }
+    
+    // Lines look like `speed = 2.5`, and `#` starts a comment. Settings that
+    // aren't listed keep their defaults.
+    pub fn parse<R: BufRead>(reader: R) -> Res<Crawler> {
+        let mut crawler = Crawler::new();
+        
+        for (i, line) in reader.lines().enumerate() {
+            let line = try!(line);
+            let line_num = i as u32 + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            
+            let mut split = line.splitn(2, '=');
+            let key = split.next().unwrap().trim();
+            let value = try!(split.next().ok_or(Error::BadLine(line_num))).trim();
+            match crawler.set(key, value) {
+                Ok(()) => {},
+                Err(SettingError::Unknown) => return Err(Error::UnknownSetting(line_num, key.into())),
+                Err(SettingError::BadValue) => return Err(Error::BadValue(line_num, value.into())),
+            }
+        }
+        
+        Ok(crawler)
+    }
+    
+    pub fn load(path: &str) -> Res<Crawler> {
+        use std::fs::File;
+        use std::io::BufReader;
+        let file = try!(File::open(path));
+        Crawler::parse(BufReader::new(file))
+    }
+}
+
+fn parse_value<T: ::std::str::FromStr>(value: &str) -> Result<T, SettingError> {
+    value.parse().map_err(|_| SettingError::BadValue)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SettingError {
+    Unknown,
+    BadValue,
+}
+
+pub type Res<T> = Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    BadLine(u32),
+    UnknownSetting(u32, String),
+    BadValue(u32, String),
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(io: io::Error) -> Error {
+        Error::Io(io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Crawler, Error, SettingError};
+    
+    #[test]
+    fn parse() {
+        let crawler = Crawler::parse(&b"# A fast one\nspeed = 4.5\ndirection = right\nturn_at_ledges = false\n"[..]);
+        assert_eq!(crawler.unwrap(), Crawler {
+            speed: 4.5,
+            direction: 1.0,
+            turn_at_ledges: false,
+            ..Crawler::new()
+        });
+    }
+    
+    #[test]
+    fn errors() {
+        match Crawler::parse(&b"speed = 1\nfly = true\n"[..]) {
+            Err(Error::UnknownSetting(2, ref key)) if key == "fly" => {},
+            other => panic!("expected an unknown setting, got {:?}", other),
+        }
+        match Crawler::parse(&b"damage = lots\n"[..]) {
+            Err(Error::BadValue(1, ref value)) if value == "lots" => {},
+            other => panic!("expected a bad value, got {:?}", other),
+        }
+        match Crawler::parse(&b"stompable\n"[..]) {
+            Err(Error::BadLine(1)) => {},
+            other => panic!("expected a bad line, got {:?}", other),
+        }
+        assert_eq!(Crawler::new().set("direction", "up"), Err(SettingError::BadValue));
+    }
+}
@@ -0,0 +1,5 @@
+#[derive(Copy, Clone, Debug)]
+pub struct Drag {
+    // Fraction of the velocity lost per second
+    pub drag: f32,
+}
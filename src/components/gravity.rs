@@ -0,0 +1,6 @@
+use cgmath::Vector2;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Gravity {
+    pub gravity: Vector2<f32>,
+}
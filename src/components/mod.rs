@@ -1,19 +1,40 @@
+pub use self::collider::Collider;
+pub use self::crawler::Crawler;
+pub use self::drag::Drag;
+pub use self::gravity::Gravity;
+pub use self::player_controller::PlayerController;
 pub use self::position::Position;
 pub use self::sprite::Sprite;
 pub use self::tint::Tint;
+pub use self::trigger::Trigger;
 pub use self::velocity::Velocity;
+pub use self::z_order::{Layer, ZOrder};
 
+pub mod collider;
+pub mod crawler;
+pub mod drag;
+pub mod gravity;
+pub mod player_controller;
 pub mod position;
 pub mod sprite;
 pub mod tint;
+pub mod trigger;
 pub mod velocity;
+pub mod z_order;
 
 components! {
     struct GameComponents {
         #[hot] position: position::Position,
         #[hot] velocity: velocity::Velocity,
+        #[hot] gravity: gravity::Gravity,
+        #[hot] drag: drag::Drag,
+        #[hot] collider: collider::Collider,
         #[hot] sprite: sprite::Sprite,
         #[cold] tint: tint::Tint,
+        #[cold] z_order: z_order::ZOrder,
         #[cold] camera_follow: (),
+        #[cold] player_controller: player_controller::PlayerController,
+        #[cold] crawler: crawler::Crawler,
+        #[cold] trigger: trigger::Trigger,
     }
 }
@@ -0,0 +1,58 @@
+use cgmath::Vector2;
+
+#[derive(Copy, Clone, Debug)]
+pub struct PlayerController {
+    // Horizontal speeds are in tiles per second
+    pub max_run_speed: f32,
+    pub acceleration: f32,
+    pub air_acceleration: f32,
+    pub deceleration: f32,
+    
+    // Upward speed when a jump starts
+    pub jump_speed: f32,
+    // Upward speed is multiplied by this when jump is released early
+    pub jump_cut: f32,
+    // Seconds after walking off a ledge that a jump still works
+    pub coyote_time: f32,
+    // Seconds before landing that a jump press is remembered
+    pub jump_buffer: f32,
+    
+    // Upward speed after stomping on an enemy
+    pub stomp_bounce: f32,
+    // Speed away from whatever damaged the player
+    pub knockback: Vector2<f32>,
+    // Seconds of invulnerability after taking damage
+    pub invulnerable_time: f32,
+    
+    pub coyote_timer: f32,
+    pub buffer_timer: f32,
+    pub jumping: bool,
+    pub facing: f32,
+    pub invulnerable_timer: f32,
+}
+
+impl PlayerController {
+    pub fn new() -> PlayerController {
+        PlayerController {
+            max_run_speed: 6.0,
+            acceleration: 60.0,
+            air_acceleration: 35.0,
+            deceleration: 50.0,
+            
+            jump_speed: 14.0,
+            jump_cut: 0.5,
+            coyote_time: 0.1,
+            jump_buffer: 0.12,
+            
+            stomp_bounce: 10.0,
+            knockback: Vector2::new(6.0, 8.0),
+            invulnerable_time: 1.5,
+            
+            coyote_timer: 0.0,
+            buffer_timer: 0.0,
+            jumping: false,
+            facing: 1.0,
+            invulnerable_timer: 0.0,
+        }
+    }
+}
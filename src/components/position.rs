@@ -3,4 +3,26 @@ use cgmath::Point2;
 #[derive(Copy, Clone, Debug)]
 pub struct Position {
     pub position: Point2<f32>,
+    // Where the entity was at the start of the last simulation step
+    pub previous: Point2<f32>,
+}
+
+impl Position {
+    pub fn new(position: Point2<f32>) -> Position {
+        Position {
+            position: position,
+            previous: position,
+        }
+    }
+    
+    // Blends between the last two simulation steps for rendering
+    pub fn interpolated(&self, alpha: f32) -> Point2<f32> {
+        self.previous + (self.position - self.previous) * alpha
+    }
+    
+    // Moves without interpolating across the gap
+    pub fn teleport(&mut self, position: Point2<f32>) {
+        self.position = position;
+        self.previous = position;
+    }
 }
@@ -1,41 +1,63 @@
 use std::sync::Arc;
 use std::path::Path;
-use image::{self, ImageResult};
+use image::{self, ImageError, ImageResult};
 use cgmath::{Point2, Vector2, Vector3, Matrix4, Quaternion, Rotation3, rad};
 use glium;
 use glium::texture::Texture2dArray;
 use glium::backend::glutin_backend::GlutinFacade;
+use world::aabb::Aabb;
+use assets::{animation, AnimationSet, AtlasRegion, Clip, PlayMode, TextureCache};
 
+#[derive(Clone)]
 pub struct Sprite {
     pub size: Vector2<f32>,
     pub scale: f32,
     pub rotation: f32,
     
-    pub animation_length: f64,
+    // Clips and the frames they show, shared between sprites
+    pub animations: Arc<AnimationSet>,
+    // Index of the playing clip
+    pub clip: usize,
+    // Seconds since the clip started, wrapped to its period unless it plays
+    // once
     pub animation_time: f64,
-    
-    pub texture: Arc<Texture2dArray>,
 }
 
 impl Sprite {
-    pub fn load<'a, I: 'a>(image_paths: I, display: &GlutinFacade, anim_len: f64)
+    // A sprite with a single looping clip that shows each image in turn
+    pub fn load<'a, I: 'a>(image_paths: I, textures: &mut TextureCache, anim_len: f64)
         -> ImageResult<Sprite>
         where I: IntoIterator, I::Item: AsRef<Path> {
         
-        let tex = try!(Sprite::load_spriteset(image_paths, display));
-        
-        Ok(Sprite {
+        let mut frames = Vec::new();
+        for image_path in image_paths {
+            frames.push(try!(textures.region(image_path)));
+        }
+        let animations = AnimationSet::uniform(frames, "idle", anim_len);
+        Ok(Sprite::new(Arc::new(animations)))
+    }
+    
+    // A sprite with the clips from an animation file, playing the first one
+    pub fn load_animations(path: &str, textures: &mut TextureCache) -> animation::Res<Sprite> {
+        let animations = try!(AnimationSet::load(path, textures));
+        Ok(Sprite::new(Arc::new(animations)))
+    }
+    
+    // The frames are regions of the texture cache's atlas, which is looked up
+    // when drawing
+    pub fn new(animations: Arc<AnimationSet>) -> Sprite {
+        Sprite {
             size: Vector2::new(1.0, 1.0),
             scale: 1.0,
             rotation: 0.0,
             
-            animation_length: anim_len,
+            animations: animations,
+            clip: 0,
             animation_time: 0.0,
-            
-            texture: tex,
-        })
+        }
     }
     
+    // Images of the same size as layers of one texture, for tilesets
     pub fn load_spriteset<'a, I: 'a>(image_paths: I, display: &GlutinFacade)
         -> ImageResult<Arc<Texture2dArray>>
         where I: IntoIterator, I::Item: AsRef<Path> {
@@ -49,19 +71,50 @@ impl Sprite {
             );
             images.push(image);
         }
-        Ok(Arc::new(Texture2dArray::new(display, images).unwrap()))
+        // Every image in an array has to be the same size
+        let texture = try!(Texture2dArray::new(display, images).map_err(|_| ImageError::DimensionError));
+        Ok(Arc::new(texture))
     }
     
-    pub fn animation_frame(&self) -> u32 {
-        let frames = self.texture.array_size();
-        (self.animation_time / self.animation_length * frames as f64) as u32
+    pub fn current_clip(&self) -> &Clip {
+        &self.animations.clips()[self.clip]
+    }
+    
+    // Switches to a clip by name, starting it over unless it's already
+    // playing. Returns false if the sprite has no such clip.
+    pub fn play(&mut self, name: &str) -> bool {
+        match self.animations.find(name) {
+            Some(clip) => {
+                if clip != self.clip {
+                    self.clip = clip;
+                    self.animation_time = 0.0;
+                }
+                true
+            },
+            None => false,
+        }
+    }
+    
+    // Whether a clip that plays once has reached its last frame
+    pub fn finished(&self) -> bool {
+        self.current_clip().finished(self.animation_time)
+    }
+    
+    pub fn animation_frame(&self) -> AtlasRegion {
+        let frame = self.current_clip().frame_at(self.animation_time);
+        self.animations.frames()[frame as usize]
     }
     
     pub fn update(&mut self, dt: f64) {
         self.animation_time += dt;
+        let clip = &self.animations.clips()[self.clip];
+        if clip.mode == PlayMode::Once {
+            return;
+        }
+        let period = clip.period();
         // I couldn't find an fmod function anywhere :(
-        while self.animation_time >= self.animation_length {
-            self.animation_time -= self.animation_length;
+        while self.animation_time >= period {
+            self.animation_time -= period;
         }
     }
     
@@ -74,4 +127,15 @@ impl Sprite {
         
         cam_matrix * sprite_mat
     }
+    
+    // The area the sprite covers when drawn at a position, including rotation
+    pub fn bounds(&self, position: &Point2<f32>) -> Aabb {
+        let size = self.size * self.scale;
+        let (sin, cos) = self.rotation.sin_cos();
+        let half_size = Vector2::new(
+            (size.x * cos).abs() + (size.y * sin).abs(),
+            (size.x * sin).abs() + (size.y * cos).abs(),
+        ) / 2.0;
+        Aabb::new(*position, half_size)
+    }
 }
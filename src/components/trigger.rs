@@ -0,0 +1,25 @@
+use cgmath::Vector2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TriggerKind {
+    Checkpoint,
+    Goal,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Trigger {
+    pub kind: TriggerKind,
+    pub half_size: Vector2<f32>,
+    // Set once the player has touched it
+    pub triggered: bool,
+}
+
+impl Trigger {
+    pub fn new(kind: TriggerKind) -> Trigger {
+        Trigger {
+            kind: kind,
+            half_size: Vector2::new(0.5, 0.5),
+            triggered: false,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::cmp::Ordering;
+
+// Groups of sprites, drawn in the order listed
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    // Drawn over decoration layers ordered behind the main layer, but
+    // before the main layer, so its tiles cover them
+    Background,
+    Pickups,
+    Enemies,
+    Player,
+    Particles,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ZOrder {
+    pub layer: Layer,
+    // Sprites in a layer draw from the lowest key up. Without a key the
+    // sprite is sorted by height, so lower sprites overlap higher ones.
+    pub key: Option<f32>,
+}
+
+impl ZOrder {
+    pub fn new(layer: Layer) -> ZOrder {
+        ZOrder {
+            layer: layer,
+            key: None,
+        }
+    }
+    
+    pub fn with_key(layer: Layer, key: f32) -> ZOrder {
+        ZOrder {
+            layer: layer,
+            key: Some(key),
+        }
+    }
+    
+    // Where a sprite at height y falls in the draw order
+    pub fn sort_key(&self, y: f32) -> (Layer, f32) {
+        (self.layer, self.key.unwrap_or(-y))
+    }
+    
+    pub fn compare(a: &(Layer, f32), b: &(Layer, f32)) -> Ordering {
+        match a.0.cmp(&b.0) {
+            Ordering::Equal => a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal),
+            order => order,
+        }
+    }
+}
+
+impl Default for ZOrder {
+    // Sprites without a ZOrder draw alongside enemies
+    fn default() -> ZOrder {
+        ZOrder::new(Layer::Enemies)
+    }
+}
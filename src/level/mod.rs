@@ -0,0 +1,58 @@
+use GameData;
+use BuildData;
+use components::GameComponents;
+use components::crawler::SettingError;
+use world::tilemap::{Tilemap, Spawn};
+use world::entities::EntityType;
+
+pub use self::prefab::{Prefab, Prefabs};
+
+pub mod prefab;
+
+pub fn load_level(data: &mut GameData, tilemap: Tilemap) {
+    data.services.tilemap = tilemap;
+    data.services.tilemap_changed = true;
+    spawn_entities(data);
+}
+
+pub fn spawn_entities(data: &mut GameData) {
+    let spawns = data.services.tilemap.spawns().to_vec();
+    for spawn in spawns {
+        let mut prefab = match data.services.prefabs.get(spawn.entity) {
+            Some(prefab) => prefab.clone(),
+            None => {
+                println!("No prefab for {:?} spawn at {}, {}", spawn.entity, spawn.col, spawn.row);
+                continue;
+            }
+        };
+        for (key, value) in &spawn.properties {
+            apply_property(&mut prefab, &spawn, key, value);
+        }
+        
+        let position = data.services.tilemap.tile_position(spawn.row, spawn.col);
+        if spawn.entity == EntityType::Player {
+            data.services.stats.start_level(position);
+        }
+        
+        data.create_entity(|e: BuildData, c: &mut GameComponents| {
+            prefab.build(e, c, position);
+        });
+    }
+}
+
+// Spawn properties from the map override the prefab's settings
+fn apply_property(prefab: &mut Prefab, spawn: &Spawn, key: &str, value: &str) {
+    let result = match prefab.crawler {
+        Some(ref mut crawler) => crawler.set(key, value),
+        None => Err(SettingError::Unknown),
+    };
+    match result {
+        Ok(()) => {},
+        Err(SettingError::Unknown) => {
+            println!("Unknown property {} on {:?} spawn at {}, {}", key, spawn.entity, spawn.col, spawn.row);
+        },
+        Err(SettingError::BadValue) => {
+            println!("Bad value {} for {} on {:?} spawn at {}, {}", value, key, spawn.entity, spawn.col, spawn.row);
+        },
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use BuildData;
+use components::*;
+use components::trigger::TriggerKind;
+use world::entities::EntityType;
+use assets::animation;
+use cgmath::{Point2, Vector2, Vector4};
+use assets::TextureCache;
+
+#[derive(Clone)]
+pub struct Prefab {
+    pub sprite: Option<Sprite>,
+    pub tint: Option<Tint>,
+    pub z_order: Option<ZOrder>,
+    pub velocity: Option<Velocity>,
+    pub gravity: Option<Gravity>,
+    pub drag: Option<Drag>,
+    pub collider: Option<Collider>,
+    pub player_controller: Option<PlayerController>,
+    pub crawler: Option<Crawler>,
+    pub trigger: Option<Trigger>,
+    pub camera_follow: bool,
+}
+
+impl Prefab {
+    pub fn new() -> Prefab {
+        Prefab {
+            sprite: None,
+            tint: None,
+            z_order: None,
+            velocity: None,
+            gravity: None,
+            drag: None,
+            collider: None,
+            player_controller: None,
+            crawler: None,
+            trigger: None,
+            camera_follow: false,
+        }
+    }
+    
+    pub fn build(&self, e: BuildData, data: &mut GameComponents, position: Point2<f32>) {
+        data.position.add(&e, Position::new(position));
+        
+        if let Some(ref sprite) = self.sprite {
+            data.sprite.add(&e, sprite.clone());
+        }
+        if let Some(tint) = self.tint {
+            data.tint.add(&e, tint);
+        }
+        if let Some(z_order) = self.z_order {
+            data.z_order.add(&e, z_order);
+        }
+        if let Some(velocity) = self.velocity {
+            data.velocity.add(&e, velocity);
+        }
+        if let Some(gravity) = self.gravity {
+            data.gravity.add(&e, gravity);
+        }
+        if let Some(drag) = self.drag {
+            data.drag.add(&e, drag);
+        }
+        if let Some(collider) = self.collider {
+            data.collider.add(&e, collider);
+        }
+        if let Some(player_controller) = self.player_controller {
+            data.player_controller.add(&e, player_controller);
+        }
+        if let Some(crawler) = self.crawler {
+            data.crawler.add(&e, crawler);
+        }
+        if let Some(trigger) = self.trigger {
+            data.trigger.add(&e, trigger);
+        }
+        if self.camera_follow {
+            data.camera_follow.add(&e, ());
+        }
+    }
+}
+
+pub struct Prefabs {
+    prefabs: HashMap<EntityType, Prefab>,
+}
+
+impl Prefabs {
+    pub fn new() -> Prefabs {
+        Prefabs {
+            prefabs: HashMap::new(),
+        }
+    }
+    
+    pub fn load_default(textures: &mut TextureCache) -> animation::Res<Prefabs> {
+        let sprite = try!(Sprite::load(&["assets/textures/wat.png"], textures, 1.0));
+        let player = try!(Sprite::load_animations("assets/animations/player.cfg", textures));
+        let tinted = |r, g, b| Some(Tint { tint: Vector4::new(r, g, b, 1.0) });
+        let crawler = Crawler::load("assets/enemies/crawler.cfg").unwrap_or_else(|e| {
+            println!("Using default crawler settings: {:?}", e);
+            Crawler::new()
+        });
+        let body = Prefab {
+            velocity: Some(Velocity { velocity: Vector2::new(0.0, 0.0) }),
+            gravity: Some(Gravity { gravity: Vector2::new(0.0, -30.0) }),
+            ..Prefab::new()
+        };
+        
+        let mut prefabs = Prefabs::new();
+        prefabs.register(EntityType::Player, Prefab {
+            sprite: Some(player),
+            collider: Some(Collider::new(0.8, 0.9)),
+            z_order: Some(ZOrder::new(Layer::Player)),
+            player_controller: Some(PlayerController::new()),
+            camera_follow: true,
+            ..body.clone()
+        });
+        prefabs.register(EntityType::Crawler, Prefab {
+            sprite: Some(sprite.clone()),
+            tint: tinted(1.0, 0.4, 0.4),
+            z_order: Some(ZOrder::new(Layer::Enemies)),
+            collider: Some(Collider::new(0.9, 0.6)),
+            crawler: Some(crawler),
+            ..body.clone()
+        });
+        prefabs.register(EntityType::Checkpoint, Prefab {
+            sprite: Some(sprite.clone()),
+            tint: tinted(0.4, 0.6, 1.0),
+            z_order: Some(ZOrder::new(Layer::Pickups)),
+            trigger: Some(Trigger::new(TriggerKind::Checkpoint)),
+            ..Prefab::new()
+        });
+        prefabs.register(EntityType::Goal, Prefab {
+            sprite: Some(sprite.clone()),
+            tint: tinted(1.0, 0.9, 0.3),
+            z_order: Some(ZOrder::new(Layer::Pickups)),
+            trigger: Some(Trigger::new(TriggerKind::Goal)),
+            ..Prefab::new()
+        });
+        
+        Ok(prefabs)
+    }
+    
+    pub fn register(&mut self, entity_type: EntityType, prefab: Prefab) {
+        self.prefabs.insert(entity_type, prefab);
+    }
+    
+    pub fn get(&self, entity_type: EntityType) -> Option<&Prefab> {
+        self.prefabs.get(&entity_type)
+    }
+}
@@ -0,0 +1,70 @@
+#![feature(io)]
+
+#[macro_use] extern crate ecs;
+#[macro_use] extern crate glium;
+
+extern crate cgmath;
+extern crate rustc_serialize;
+extern crate time;
+extern crate image;
+extern crate xml;
+
+use components::GameComponents;
+
+pub mod assets;
+pub mod world;
+pub mod components;
+pub mod systems;
+pub mod level;
+
+#[cfg(test)]
+mod test_util;
+
+pub type GameData = ecs::DataHelper<GameComponents, systems::Services>;
+pub type GameWorld = ecs::World<systems::GameSystems>;
+pub type BuildData<'a> = ecs::BuildData<'a, GameComponents>;
+
+// Samples time and input, catches the simulation up and then draws
+pub fn run_frame(world: &mut GameWorld) {
+    world.systems.begin_frame(&mut world.data);
+    while world.data.services.timestep.next_step() {
+        world.systems.simulate(&mut world.data);
+    }
+    world.update();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run_frame;
+    use test_util::{headless_world, parse_map};
+    
+    // The player starts three tiles above the floor
+    const MAP: &'static str = "6 5\n\
+                               _____\n\
+                               __2__\n\
+                               _____\n\
+                               _____\n\
+                               _____\n\
+                               #####\n";
+    
+    #[test]
+    fn player_lands_headless() {
+        let mut world = headless_world(parse_map(MAP));
+        
+        for _ in 0..10 {
+            run_frame(&mut world);
+        }
+        let falling = world.services.player.expect("the player never moved");
+        assert!(falling.bounds.center.y < 4.0);
+        assert!(falling.velocity.y < 0.0);
+        
+        for _ in 0..230 {
+            run_frame(&mut world);
+        }
+        let landed = world.services.player.unwrap();
+        // The floor's top edge is at 0.5
+        assert!((landed.bounds.bottom() - 0.5).abs() < 0.01, "player stopped at {:?}", landed.bounds);
+        assert!(landed.velocity.y.abs() < 1.0);
+        assert_eq!(world.services.stats.lives, 3);
+    }
+}
@@ -1,90 +1,179 @@
-#![feature(io)]
+extern crate ecs_game;
+extern crate glium;
 
-#[macro_use] extern crate ecs;
-#[macro_use] extern crate glium;
+use ecs_game::{systems, level, run_frame, GameWorld};
 
-extern crate cgmath;
-extern crate rustc_serialize;
-extern crate time;
-extern crate image;
-
-use components::GameComponents;
-use glium::DrawParameters;
-
-pub mod world;
-pub mod components;
-pub mod systems;
+// Headless runs have no window or GL context, so everything except drawing
+// works on machines without a display
+struct Options {
+    headless: bool,
+    frames: Option<u64>,
+    level: String,
+}
 
-pub type GameData = ecs::DataHelper<GameComponents, systems::Services>;
-pub type GameWorld = ecs::World<systems::GameSystems>;
-pub type BuildData<'a> = ecs::BuildData<'a, GameComponents>;
+impl Options {
+    fn from_args() -> Options {
+        let mut options = Options {
+            headless: false,
+            frames: None,
+            level: "assets/levels/level1.txt".into(),
+        };
+        
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match &arg[..] {
+                "--headless" => options.headless = true,
+                "--frames" => {
+                    options.frames = args.next().and_then(|n| n.parse().ok());
+                },
+                "--level" => {
+                    if let Some(level) = args.next() {
+                        options.level = level;
+                    }
+                },
+                _ => println!("Unknown argument {}", arg),
+            }
+        }
+        
+        // A headless run has no window to close, so it needs an end
+        if options.headless && options.frames.is_none() {
+            options.frames = Some(600);
+        }
+        options
+    }
+}
 
 fn main() {
     use glium::DisplayBuild;
-    use world::tilemap::load_map;
-    use cgmath::Point2;
-    use components::*;
+    use ecs_game::assets;
+    use ecs_game::world::tilemap::{self, load_map};
+    use ecs_game::world::tiled::load_tiled;
+    use ecs_game::world::binary;
+    use ecs_game::world::tileset::Tileset;
+    use ecs_game::components::*;
     
-    let display = glium::glutin::WindowBuilder::new()
-        .with_min_dimensions(800, 480)
-        .with_dimensions(1280, 720)
-        .with_title("ECS Game".into())
-        .build_glium()
-        .unwrap();
-        
-    let tileset = Sprite::load_spriteset(
-        &[
-            "assets/tilesets/basic/wall.png",
-            "assets/tilesets/basic/breakable.png",
-        ],
-        &display,
-    ).unwrap();
+    let options = Options::from_args();
     
-    let services = systems::Services {
-        delta_time: 0.0,
-        running_time: -1.0,
-        running: true,
-        tilemap_changed: true,
-        tilemap: load_map("assets/levels/level1.txt"),
-        tileset: tileset,
-        display: display,
-        frame: None,
-        camera: systems::graphics::Camera::new(),
-        draw_params: DrawParameters {
-            blend: glium::Blend {
-                color: glium::BlendingFunction::Addition {
-                    source: glium::LinearBlendingFactor::SourceAlpha,
-                    destination: glium::LinearBlendingFactor::OneMinusSourceAlpha
-                },
-                alpha: glium::BlendingFunction::Addition {
-                    source: glium::LinearBlendingFactor::SourceAlpha,
-                    destination: glium::LinearBlendingFactor::OneMinusSourceAlpha
-                },
-                constant_value: (1.0, 1.0, 1.0, 1.0)
-            },
-            
-            ..Default::default()
+    let tiles_path = "assets/tilesets/basic/tiles.cfg";
+    let tiles = match Tileset::load(tiles_path) {
+        Ok(tiles) => tiles,
+        Err(e) => {
+            println!("Couldn't load tileset: {}", tilemap::Error::in_file(tiles_path, From::from(e)));
+            return;
+        }
+    };
+    
+    // Anything that isn't a text or binary level came from Tiled
+    let loaded = if options.level.ends_with(".txt") {
+        load_map(&options.level, &tiles)
+    } else if options.level.ends_with(".lvl") {
+        binary::load_file(&options.level, &tiles)
+    } else {
+        load_tiled(&options.level, &tiles)
+    };
+    let tilemap = match loaded {
+        Ok(tilemap) => tilemap,
+        Err(e) => {
+            println!("Couldn't load level: {}", e);
+            return;
+        }
+    };
+    
+    let display = if options.headless {
+        None
+    } else {
+        Some(glium::glutin::WindowBuilder::new()
+            .with_min_dimensions(800, 480)
+            .with_dimensions(1280, 720)
+            .with_title("ECS Game".into())
+            .build_glium()
+            .unwrap())
+    };
+    
+    let mut tileset = None;
+    let mut layer_tilesets = Vec::new();
+    if let Some(ref display) = display {
+        match Sprite::load_spriteset(tiles.textures(), display) {
+            Ok(texture) => tileset = Some(texture),
+            Err(e) => {
+                println!("Couldn't load tileset images: {}", e);
+                return;
+            }
+        }
+        for layer in tilemap.layers() {
+            match Sprite::load_spriteset(layer.tileset(), display) {
+                Ok(texture) => layer_tilesets.push(texture),
+                Err(e) => {
+                    println!("Couldn't load tileset images for layer {}: {}", layer.name(), e);
+                    return;
+                }
+            }
         }
+    }
+    
+    let mut textures = assets::TextureCache::new(display.as_ref());
+    let prefabs = match level::Prefabs::load_default(&mut textures) {
+        Ok(prefabs) => prefabs,
+        Err(e) => {
+            println!("Couldn't load sprites: {}", e);
+            return;
+        }
+    };
+    
+    let renderer: Box<systems::graphics::Renderer> = match display {
+        Some(ref display) => Box::new(systems::graphics::render::GliumRenderer::new(display)),
+        None => Box::new(systems::graphics::render::Recorder::new(16.0 / 9.0)),
     };
     
+    let bindings = systems::input::Bindings::load("assets/config/bindings.cfg")
+        .unwrap_or_else(|e| {
+            println!("Using default key bindings: {:?}", e);
+            Default::default()
+        });
+    
+    let mut services = systems::Services::new(tilemap, prefabs, textures, renderer);
+    services.actions = systems::input::Actions::new(bindings);
+    services.tileset = tileset;
+    services.layer_tilesets = layer_tilesets;
+    services.display = display;
+    
     let mut world = GameWorld::with_services(services);
+    if options.headless {
+        // Without real frames to pace it, step the simulation once per frame
+        let step = world.services.timestep.step;
+        world.services.timestep.fixed_frame_time = Some(step);
+    }
     
-    let display = world.services.display.clone();
-    world.create_entity(|e: BuildData, data: &mut GameComponents| {
-        data.position.add(&e, Position { position: Point2::new(3.0, 1.0) });
-        data.camera_follow.add(&e, ());
-        data.sprite.add(&e, Sprite::load(
-            &["assets/textures/wat.png"],
-            &display,
-            1.0,
-        ).unwrap());
-    });
+    level::spawn_entities(&mut world.data);
     
-    world.update();
+    run_frame(&mut world);
     
     println!("{:?}", world.services.camera);
     
+    let mut frames = 1;
     while world.services.running {
-        world.update();
+        if options.frames.map(|max| frames >= max).unwrap_or(false) {
+            break;
+        }
+        run_frame(&mut world);
+        frames += 1;
+        
+        if world.services.stats.game_over {
+            println!("Game over with {} coins", world.services.stats.coins);
+            break;
+        }
+    }
+    
+    if options.headless {
+        let stats = &world.services.stats;
+        println!("Ran {} frames: {} lives, {} coins, level complete: {}",
+            frames, stats.lives, stats.coins, stats.level_complete);
+        if let Some(recorder) = world.services.renderer.recorder() {
+            println!("{} draw commands in the last frame", recorder.commands().len());
+        }
+        let culling = &world.services.culling;
+        println!("Sprites: {} drawn, {} culled; terrain chunks: {} drawn, {} culled",
+            culling.sprites_drawn, culling.sprites_culled,
+            culling.chunks_drawn, culling.chunks_culled);
     }
 }
@@ -0,0 +1,64 @@
+use std::mem;
+use std::slice;
+use GameData;
+use systems::Services;
+use components::GameComponents;
+use world::item::Item;
+use cgmath::Point2;
+use ecs::{System, Process};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    BlockBroken { row: u32, col: u32, item: Item },
+    EnemyStomped { position: Point2<f32> },
+    PlayerDamaged { damage: u32, source: Point2<f32> },
+    PlayerFell,
+    PlayerRespawn { position: Point2<f32> },
+    CoinsCollected { total: u32 },
+    LifeGained { lives: u32 },
+    LifeLost { lives: u32 },
+    CheckpointReached { position: Point2<f32> },
+    GoalReached,
+    GameOver,
+}
+
+// Events pushed during one update can be read by every system in the next
+pub struct Events {
+    current: Vec<GameEvent>,
+    pending: Vec<GameEvent>,
+}
+
+impl Events {
+    pub fn new() -> Events {
+        Events {
+            current: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+    
+    pub fn push(&mut self, event: GameEvent) {
+        self.pending.push(event);
+    }
+    
+    pub fn iter(&self) -> slice::Iter<GameEvent> {
+        self.current.iter()
+    }
+    
+    pub fn flush(&mut self) {
+        mem::swap(&mut self.current, &mut self.pending);
+        self.pending.clear();
+    }
+}
+
+pub struct FlushEvents;
+
+impl Process for FlushEvents {
+    fn process(&mut self, data: &mut GameData) {
+        data.services.events.flush();
+    }
+}
+
+impl System for FlushEvents {
+    type Components = GameComponents;
+    type Services = Services;
+}
@@ -0,0 +1,50 @@
+use GameData;
+use systems::Services;
+use systems::events::GameEvent;
+use systems::input::Action;
+use components::GameComponents;
+use ecs::{System, EntityIter};
+use ecs::system::entity::EntityProcess;
+use cgmath::Vector2;
+
+pub struct BreakBlocks;
+
+impl EntityProcess for BreakBlocks {
+    fn process(&mut self, entities: EntityIter<GameComponents>, data: &mut GameData) {
+        for e in entities {
+            let position = data.components.position[e].position;
+            let collider = data.components.collider[e];
+            let facing = data.components.player_controller[e].facing;
+            
+            let mut targets = Vec::new();
+            
+            // Jumping into a block from below
+            if let Some(tile) = collider.ceiling_tile {
+                targets.push(tile);
+            }
+            
+            // Attacking the block directly in front
+            if data.services.actions.pressed(Action::Attack) {
+                let reach = Vector2::new(facing * (collider.half_size.x + 0.5), 0.0);
+                if let Some(tile) = data.services.tilemap.tile_coords(position + reach) {
+                    targets.push(tile);
+                }
+            }
+            
+            for (row, col) in targets {
+                if let Some(item) = data.services.tilemap.break_tile(row, col) {
+                    data.services.events.push(GameEvent::BlockBroken {
+                        row: row,
+                        col: col,
+                        item: item,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl System for BreakBlocks {
+    type Components = GameComponents;
+    type Services = Services;
+}
@@ -9,8 +9,9 @@ pub struct CameraFollow;
 
 impl EntityProcess for CameraFollow {
     fn process(&mut self, entities: EntityIter<GameComponents>, data: &mut GameData) {
+        let alpha = data.services.timestep.alpha() as f32;
         for e in entities {
-            let pos = data.components.position[e].position.to_vec();
+            let pos = data.components.position[e].interpolated(alpha).to_vec();
             let center = (data.services.camera.center + pos) / 2.0;
             data.services.camera.center = center;
         }
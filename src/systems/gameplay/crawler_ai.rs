@@ -0,0 +1,76 @@
+use GameData;
+use systems::Services;
+use systems::events::GameEvent;
+use components::GameComponents;
+use ecs::{System, EntityIter};
+use ecs::system::entity::EntityProcess;
+use cgmath::Vector2;
+
+pub struct CrawlerAi;
+
+impl EntityProcess for CrawlerAi {
+    fn process(&mut self, entities: EntityIter<GameComponents>, data: &mut GameData) {
+        for e in entities {
+            let position = data.components.position[e].position;
+            let collider = data.components.collider[e];
+            let mut crawler = data.components.crawler[e];
+            if crawler.defeated {
+                continue;
+            }
+            
+            // Turn around when blocked or about to walk off an edge
+            let blocked = if crawler.direction < 0.0 {
+                collider.wall_left
+            } else {
+                collider.wall_right
+            };
+            let ahead = Vector2::new(crawler.direction * (collider.half_size.x + 0.1), 0.0);
+            let below = Vector2::new(0.0, -(collider.half_size.y + 0.5));
+            let ledge = collider.grounded && !data.services.tilemap
+                .tile_coords(position + ahead + below)
+                .map(|(row, col)| {
+                    data.services.tilemap.filled_at(row, col) ||
+                        data.services.tilemap.one_way_at(row, col)
+                })
+                .unwrap_or(false);
+            
+            if (crawler.turn_at_walls && blocked) || (crawler.turn_at_ledges && ledge) {
+                crawler.direction = -crawler.direction;
+            }
+            
+            data.components.velocity[e].velocity.x = crawler.direction * crawler.speed;
+            data.components.crawler[e] = crawler;
+            
+            // Player contact
+            let player = match data.services.player {
+                Some(player) => player,
+                None => continue,
+            };
+            let bounds = collider.aabb(position);
+            if !player.bounds.overlaps(&bounds) {
+                continue;
+            }
+            
+            // Coming down on the top half counts as a stomp, anything else hurts
+            let stomped = crawler.stompable && player.velocity.y < 0.0 &&
+                player.bounds.center.y > bounds.center.y + bounds.half_size.y / 2.0;
+            if stomped {
+                // Removal waits for the end of the frame, which may be several
+                // simulation steps away
+                data.components.crawler[e].defeated = true;
+                data.services.events.push(GameEvent::EnemyStomped { position: position });
+                data.remove_entity(**e);
+            } else if !player.invulnerable {
+                data.services.events.push(GameEvent::PlayerDamaged {
+                    damage: crawler.damage,
+                    source: position,
+                });
+            }
+        }
+    }
+}
+
+impl System for CrawlerAi {
+    type Components = GameComponents;
+    type Services = Services;
+}
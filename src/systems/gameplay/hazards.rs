@@ -0,0 +1,48 @@
+use GameData;
+use systems::Services;
+use systems::events::GameEvent;
+use components::GameComponents;
+use world::aabb::Aabb;
+use ecs::{System, Process};
+use cgmath::Vector2;
+
+// How far outside the player's box a tile still hurts, so that standing on a
+// hazard counts as touching it
+const REACH: f32 = 0.05;
+
+pub struct TileHazards;
+
+impl Process for TileHazards {
+    fn process(&mut self, data: &mut GameData) {
+        let player = match data.services.player {
+            Some(player) if !player.invulnerable => player,
+            _ => return,
+        };
+        let tilemap = &data.services.tilemap;
+        let bounds = player.bounds;
+        
+        let reach = Aabb::new(bounds.center, bounds.half_size + Vector2::new(REACH, REACH));
+        
+        // Only the worst hazard touched counts
+        let mut worst: Option<(u32, u32, u32)> = None;
+        for (r, c) in tilemap.tiles_overlapping(&reach) {
+            let damage = tilemap.damage_at(r, c);
+            if damage > worst.map(|(d, _, _)| d).unwrap_or(0) {
+                worst = Some((damage, r, c));
+            }
+        }
+        
+        if let Some((damage, r, c)) = worst {
+            let source = tilemap.tile_position(r, c);
+            data.services.events.push(GameEvent::PlayerDamaged {
+                damage: damage,
+                source: source,
+            });
+        }
+    }
+}
+
+impl System for TileHazards {
+    type Components = GameComponents;
+    type Services = Services;
+}
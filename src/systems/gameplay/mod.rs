@@ -1,3 +1,17 @@
+pub use self::break_blocks::BreakBlocks;
 pub use self::camera_follow::CameraFollow;
+pub use self::crawler_ai::CrawlerAi;
+pub use self::hazards::TileHazards;
+pub use self::player_animation::PlayerAnimation;
+pub use self::player_control::{PlayerControl, PlayerBody};
+pub use self::stats::{PlayerStats, UpdateStats};
+pub use self::triggers::Triggers;
 
+pub mod break_blocks;
 pub mod camera_follow;
+pub mod crawler_ai;
+pub mod hazards;
+pub mod player_animation;
+pub mod player_control;
+pub mod stats;
+pub mod triggers;
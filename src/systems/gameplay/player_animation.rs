@@ -0,0 +1,38 @@
+use GameData;
+use systems::Services;
+use components::GameComponents;
+use ecs::{System, EntityIter};
+use ecs::system::entity::EntityProcess;
+
+// Below this horizontal speed the player counts as standing still
+const RUN_THRESHOLD: f32 = 0.1;
+
+// Picks the player's animation clip from what it's doing
+pub struct PlayerAnimation;
+
+impl EntityProcess for PlayerAnimation {
+    fn process(&mut self, entities: EntityIter<GameComponents>, data: &mut GameData) {
+        for e in entities {
+            let ctl = data.components.player_controller[e];
+            let grounded = data.components.collider[e].grounded;
+            let velocity = data.components.velocity[e].velocity;
+            
+            // Knockback keeps the player in the air until it lands
+            let clip = if ctl.invulnerable_timer > 0.0 && !grounded {
+                "hurt"
+            } else if !grounded {
+                "jump"
+            } else if velocity.x.abs() > RUN_THRESHOLD {
+                "run"
+            } else {
+                "idle"
+            };
+            data.components.sprite[e].play(clip);
+        }
+    }
+}
+
+impl System for PlayerAnimation {
+    type Components = GameComponents;
+    type Services = Services;
+}
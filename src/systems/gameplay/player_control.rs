@@ -0,0 +1,138 @@
+use GameData;
+use systems::Services;
+use systems::events::GameEvent;
+use systems::input::Action;
+use components::GameComponents;
+use world::aabb::Aabb;
+use ecs::{System, EntityIter};
+use ecs::system::entity::EntityProcess;
+use cgmath::Vector2;
+
+// How far below the bottom of the map the player can fall before losing a life
+const FALL_LIMIT: f32 = 2.0;
+
+// What other systems need to know about the player to interact with it
+#[derive(Copy, Clone, Debug)]
+pub struct PlayerBody {
+    pub bounds: Aabb,
+    pub velocity: Vector2<f32>,
+    pub invulnerable: bool,
+}
+
+pub struct PlayerControl;
+
+impl EntityProcess for PlayerControl {
+    fn process(&mut self, entities: EntityIter<GameComponents>, data: &mut GameData) {
+        let dt = data.services.delta_time as f32;
+        let actions = &data.services.actions;
+        
+        for e in entities {
+            // Input stops counting once the last life runs out
+            if data.services.stats.game_over {
+                data.components.velocity[e].velocity = Vector2::new(0.0, 0.0);
+                continue;
+            }
+            
+            let mut ctl = data.components.player_controller[e];
+            let mut position = data.components.position[e].position;
+            let collider = data.components.collider[e];
+            let grounded = collider.grounded;
+            let mut velocity = data.components.velocity[e].velocity;
+            
+            // Running
+            let input = actions.axis(Action::MoveLeft, Action::MoveRight);
+            let accel = if input == 0.0 {
+                ctl.deceleration
+            } else if grounded {
+                ctl.acceleration
+            } else {
+                ctl.air_acceleration
+            };
+            velocity.x = approach(velocity.x, input * ctl.max_run_speed, accel * dt);
+            if input != 0.0 {
+                ctl.facing = input;
+            }
+            
+            // Coyote time and jump buffering
+            if grounded {
+                ctl.coyote_timer = ctl.coyote_time;
+                ctl.jumping = false;
+            } else {
+                ctl.coyote_timer -= dt;
+            }
+            if actions.pressed(Action::Jump) {
+                ctl.buffer_timer = ctl.jump_buffer;
+            } else {
+                ctl.buffer_timer -= dt;
+            }
+            
+            if ctl.buffer_timer > 0.0 && ctl.coyote_timer > 0.0 {
+                velocity.y = ctl.jump_speed;
+                ctl.jumping = true;
+                ctl.buffer_timer = 0.0;
+                ctl.coyote_timer = 0.0;
+            }
+            
+            // Letting go of jump early cuts the rise short
+            if ctl.jumping && velocity.y > 0.0 && !actions.held(Action::Jump) {
+                velocity.y *= ctl.jump_cut;
+                ctl.jumping = false;
+            }
+            if velocity.y <= 0.0 {
+                ctl.jumping = false;
+            }
+            
+            // Enemy interactions
+            ctl.invulnerable_timer -= dt;
+            for event in data.services.events.iter() {
+                match *event {
+                    GameEvent::EnemyStomped { .. } => {
+                        velocity.y = ctl.stomp_bounce;
+                        ctl.jumping = true;
+                    },
+                    GameEvent::PlayerDamaged { source, .. } if ctl.invulnerable_timer <= 0.0 => {
+                        let away = if position.x < source.x { -1.0 } else { 1.0 };
+                        velocity = Vector2::new(away * ctl.knockback.x, ctl.knockback.y);
+                        ctl.invulnerable_timer = ctl.invulnerable_time;
+                    },
+                    GameEvent::PlayerRespawn { position: respawn } => {
+                        position = respawn;
+                        data.components.position[e].previous = respawn;
+                        velocity = Vector2::new(0.0, 0.0);
+                        ctl.jumping = false;
+                        ctl.buffer_timer = 0.0;
+                        ctl.coyote_timer = 0.0;
+                        ctl.invulnerable_timer = ctl.invulnerable_time;
+                    },
+                    _ => {}
+                }
+            }
+            
+            if position.y < -FALL_LIMIT {
+                data.services.events.push(GameEvent::PlayerFell);
+            }
+            
+            data.components.position[e].position = position;
+            data.components.velocity[e].velocity = velocity;
+            data.components.player_controller[e] = ctl;
+            data.services.player = Some(PlayerBody {
+                bounds: collider.aabb(position),
+                velocity: velocity,
+                invulnerable: ctl.invulnerable_timer > 0.0,
+            });
+        }
+    }
+}
+
+impl System for PlayerControl {
+    type Components = GameComponents;
+    type Services = Services;
+}
+
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    if value < target {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
+    }
+}
@@ -0,0 +1,167 @@
+use GameData;
+use systems::Services;
+use systems::events::GameEvent;
+use components::GameComponents;
+use world::item::Item;
+use ecs::{System, Process};
+use cgmath::Point2;
+
+pub struct PlayerStats {
+    pub lives: u32,
+    pub health: u32,
+    pub max_health: u32,
+    pub coins: u32,
+    // Every time the coin total passes a multiple of this, the player gets a life
+    pub coins_per_life: u32,
+    
+    pub spawn_point: Point2<f32>,
+    pub checkpoint: Option<Point2<f32>>,
+    pub level_complete: bool,
+    // The last life was lost; the player no longer moves or respawns
+    pub game_over: bool,
+    
+    // A life was lost and the player hasn't been put back yet
+    respawning: bool,
+}
+
+impl PlayerStats {
+    pub fn new() -> PlayerStats {
+        PlayerStats {
+            lives: 3,
+            health: 3,
+            max_health: 3,
+            coins: 0,
+            coins_per_life: 100,
+            
+            spawn_point: Point2::new(0.0, 0.0),
+            checkpoint: None,
+            level_complete: false,
+            game_over: false,
+            
+            respawning: false,
+        }
+    }
+    
+    // Clears per-level progress, keeping lives and coins
+    pub fn start_level(&mut self, spawn_point: Point2<f32>) {
+        self.spawn_point = spawn_point;
+        self.checkpoint = None;
+        self.level_complete = false;
+        self.health = self.max_health;
+        self.respawning = false;
+    }
+    
+    pub fn respawn_point(&self) -> Point2<f32> {
+        self.checkpoint.unwrap_or(self.spawn_point)
+    }
+    
+    fn add_coins(&mut self, coins: u32, events: &mut Vec<GameEvent>) {
+        let before = self.coins / self.coins_per_life;
+        self.coins += coins;
+        events.push(GameEvent::CoinsCollected { total: self.coins });
+        
+        for _ in before..self.coins / self.coins_per_life {
+            self.gain_life(events);
+        }
+    }
+    
+    fn gain_life(&mut self, events: &mut Vec<GameEvent>) {
+        self.lives += 1;
+        events.push(GameEvent::LifeGained { lives: self.lives });
+    }
+    
+    // Whether damage and falls currently count against the player
+    fn vulnerable(&self) -> bool {
+        !self.respawning && !self.game_over
+    }
+    
+    fn lose_life(&mut self, events: &mut Vec<GameEvent>) {
+        self.lives = self.lives.saturating_sub(1);
+        events.push(GameEvent::LifeLost { lives: self.lives });
+        
+        if self.lives == 0 {
+            self.game_over = true;
+            events.push(GameEvent::GameOver);
+        } else {
+            self.respawning = true;
+            events.push(GameEvent::PlayerRespawn { position: self.respawn_point() });
+        }
+    }
+}
+
+pub struct UpdateStats;
+
+impl Process for UpdateStats {
+    fn process(&mut self, data: &mut GameData) {
+        let stats = &mut data.services.stats;
+        let mut events = Vec::new();
+        let mut damaged = false;
+        
+        for event in data.services.events.iter() {
+            match *event {
+                GameEvent::BlockBroken { item: Item::Coins(coins), .. } => {
+                    stats.add_coins(coins, &mut events);
+                },
+                GameEvent::BlockBroken { item: Item::ExtraLife, .. } => {
+                    stats.gain_life(&mut events);
+                },
+                // Several enemies touching the player at once only hurt once
+                GameEvent::PlayerDamaged { damage, .. } if stats.vulnerable() && !damaged => {
+                    damaged = true;
+                    stats.health = stats.health.saturating_sub(damage);
+                    if stats.health == 0 {
+                        stats.lose_life(&mut events);
+                    }
+                },
+                GameEvent::PlayerFell if stats.vulnerable() => {
+                    stats.lose_life(&mut events);
+                },
+                GameEvent::PlayerRespawn { .. } => {
+                    stats.respawning = false;
+                    stats.health = stats.max_health;
+                },
+                GameEvent::CheckpointReached { position } => {
+                    stats.checkpoint = Some(position);
+                },
+                GameEvent::GoalReached => {
+                    stats.level_complete = true;
+                },
+                _ => {}
+            }
+        }
+        
+        for event in events {
+            data.services.events.push(event);
+        }
+    }
+}
+
+impl System for UpdateStats {
+    type Components = GameComponents;
+    type Services = Services;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PlayerStats;
+    use systems::events::GameEvent;
+    
+    #[test]
+    fn game_over() {
+        let mut stats = PlayerStats::new();
+        let mut events = Vec::new();
+        
+        stats.lose_life(&mut events);
+        assert!(stats.respawning && !stats.game_over);
+        assert_eq!(events.last(), Some(&GameEvent::PlayerRespawn { position: stats.respawn_point() }));
+        stats.respawning = false;
+        
+        stats.lose_life(&mut events);
+        stats.respawning = false;
+        events.clear();
+        stats.lose_life(&mut events);
+        assert_eq!(events, vec![GameEvent::LifeLost { lives: 0 }, GameEvent::GameOver]);
+        assert!(stats.game_over && !stats.vulnerable());
+        assert!(!stats.respawning);
+    }
+}
@@ -0,0 +1,44 @@
+use GameData;
+use systems::Services;
+use systems::events::GameEvent;
+use components::GameComponents;
+use components::trigger::TriggerKind;
+use world::aabb::Aabb;
+use ecs::{System, EntityIter};
+use ecs::system::entity::EntityProcess;
+
+pub struct Triggers;
+
+impl EntityProcess for Triggers {
+    fn process(&mut self, entities: EntityIter<GameComponents>, data: &mut GameData) {
+        let player = match data.services.player {
+            Some(player) => player,
+            None => return,
+        };
+        
+        for e in entities {
+            let position = data.components.position[e].position;
+            let mut trigger = data.components.trigger[e];
+            if trigger.triggered {
+                continue;
+            }
+            
+            let bounds = Aabb::new(position, trigger.half_size);
+            if !player.bounds.overlaps(&bounds) {
+                continue;
+            }
+            
+            data.services.events.push(match trigger.kind {
+                TriggerKind::Checkpoint => GameEvent::CheckpointReached { position: position },
+                TriggerKind::Goal => GameEvent::GoalReached,
+            });
+            trigger.triggered = true;
+            data.components.trigger[e] = trigger;
+        }
+    }
+}
+
+impl System for Triggers {
+    type Components = GameComponents;
+    type Services = Services;
+}
@@ -14,7 +14,7 @@ impl System for Animate {
 impl EntityProcess for Animate {
     fn process(&mut self, entities: EntityIter<GameComponents>, data: &mut GameData) {
         for e in entities {
-            data.components.sprite[e].update(data.services.delta_time);
+            data.components.sprite[e].update(data.services.frame_time);
         }
     }
 }
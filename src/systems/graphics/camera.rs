@@ -1,4 +1,5 @@
-use cgmath::{Point2, Matrix4, ortho};
+use cgmath::{Point2, Vector2, Matrix4, ortho};
+use world::aabb::Aabb;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Camera {
@@ -26,4 +27,9 @@ impl Camera {
             1.0,
         )
     }
+    
+    // The part of the world the camera can see
+    pub fn bounds(&self) -> Aabb {
+        Aabb::new(self.center, Vector2::new(self.viewport_size * self.aspect_ratio, self.viewport_size))
+    }
 }
@@ -0,0 +1,24 @@
+// How many things the draw systems submitted or skipped for being off screen
+// in the current frame
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CullStats {
+    pub sprites_drawn: u32,
+    pub sprites_culled: u32,
+    // Terrain is culled a chunk at a time, for every layer
+    pub chunks_drawn: u32,
+    pub chunks_culled: u32,
+}
+
+impl CullStats {
+    pub fn new() -> CullStats {
+        Default::default()
+    }
+    
+    pub fn drawn(&self) -> u32 {
+        self.sprites_drawn + self.chunks_drawn
+    }
+    
+    pub fn culled(&self) -> u32 {
+        self.sprites_culled + self.chunks_culled
+    }
+}
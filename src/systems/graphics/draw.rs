@@ -1,20 +1,16 @@
-use std::mem;
 use GameData;
 use systems::Services;
+use systems::graphics::CullStats;
 use components::GameComponents;
 use ecs::{System, Process};
-use glium::Surface;
 
 pub struct BeginDraw;
 
 impl Process for BeginDraw {
     fn process(&mut self, data: &mut GameData) {
-        let mut frame = data.services.display.draw();
-        frame.clear(None, Some((0.0, 0.0, 0.0, 0.0)), false, None, None);
-        let (width, height) = frame.get_dimensions();
-        data.services.frame = Some(frame);
-        let aspect = width as f32 / height as f32;
+        let aspect = data.services.renderer.begin_frame();
         data.services.camera.aspect_ratio = aspect;
+        data.services.culling = CullStats::new();
     }
 }
 
@@ -27,8 +23,9 @@ pub struct EndDraw;
 
 impl Process for EndDraw {
     fn process(&mut self, data: &mut GameData) {
-        let frame = mem::replace(&mut data.services.frame, None).unwrap();
-        frame.finish().unwrap();
+        data.services.renderer.end_frame();
+        // Every terrain pass has seen the change by now
+        data.services.tilemap_changed = false;
     }
 }
 
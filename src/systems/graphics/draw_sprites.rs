@@ -1,86 +1,80 @@
-use glium::{self, Surface, Program, VertexBuffer};
-use glium::index::{NoIndices, PrimitiveType};
-use cgmath::{Vector4, Matrix};
+use cgmath::{Matrix, Vector4};
 use GameData;
 use systems::Services;
-use components::GameComponents;
+use systems::graphics::render::{RenderCommand, SpriteBatch, SpriteInstance};
+use components::{GameComponents, Layer, ZOrder};
 use ecs::{System, EntityIter};
 use ecs::system::entity::EntityProcess;
 
-#[derive(Copy, Clone, Debug)]
-struct Vertex {
-    position: [f32; 2],
-    tex_coords: [f32; 2],
+// Which sprites a DrawSprites draws
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SpritePass {
+    // Sprites in the background layer, drawn between the decoration layers
+    // behind the main layer and the main layer itself
+    Background,
+    // Every other layer
+    Front,
 }
 
-implement_vertex!(Vertex, position, tex_coords);
-
 pub struct DrawSprites {
-    program: Option<Program>,
-    vertices: Option<VertexBuffer<Vertex>>,
+    pass: SpritePass,
+}
+
+impl DrawSprites {
+    pub fn new(pass: SpritePass) -> DrawSprites {
+        DrawSprites {
+            pass: pass,
+        }
+    }
 }
 
 impl EntityProcess for DrawSprites {
     fn process(&mut self, entities: EntityIter<GameComponents>, data: &mut GameData) {
-        if self.program.is_none() {
-            self.initialize(&data.services);
-        }
-        
-        let frame = data.services.frame.as_mut().unwrap();
-        let program = self.program.as_ref().unwrap();
-        let vertices = self.vertices.as_ref().unwrap();
         let cam_matrix = data.services.camera.matrix();
+        let view = data.services.camera.bounds();
+        let alpha = data.services.timestep.alpha() as f32;
         
+        let mut sprites = Vec::new();
         for e in entities {
-            let position = data.components.position[e];
+            let z_order = data.components.z_order.get(&e).unwrap_or(ZOrder::default());
+            if (z_order.layer == Layer::Background) != (self.pass == SpritePass::Background) {
+                continue;
+            }
+            
+            let position = data.components.position[e].interpolated(alpha);
             let sprite = &data.components.sprite[e];
+            if !sprite.bounds(&position).overlaps(&view) {
+                data.services.culling.sprites_culled += 1;
+                continue;
+            }
+            data.services.culling.sprites_drawn += 1;
             let tint = data.components.tint.get(&e)
                 .map(|t| t.tint).unwrap_or(Vector4::new(1.0, 1.0, 1.0, 1.0));
-            let frame_num = sprite.animation_frame();
-            let matrix = sprite.matrix(&position.position, &cam_matrix);
             
-            let uniforms = uniform! {
-                matrix: Into::<[[f32; 4]; 4]>::into(matrix.transpose()),
-                tex: sprite.texture.sampled().magnify_filter(
-                    glium::uniforms::MagnifySamplerFilter::Nearest
-                ),
-                frame: frame_num,
-                tint: [tint.x, tint.y, tint.z, tint.w],
+            let region = sprite.animation_frame();
+            let instance = SpriteInstance {
+                matrix: sprite.matrix(&position, &cam_matrix).transpose().into(),
+                frame: region.page,
+                uv: region.uv,
+                tint: tint.into(),
             };
             
-            frame.draw(
-                vertices,
-                NoIndices(PrimitiveType::TriangleStrip),
-                program,
-                &uniforms,
-                &data.services.draw_params,
-            ).unwrap();
+            sprites.push((z_order.sort_key(position.y), instance));
         }
-    }
-}
-
-impl DrawSprites {
-    pub fn new() -> DrawSprites {
-        DrawSprites {
-            program: None,
-            vertices: None,
+        if sprites.is_empty() {
+            return;
         }
-    }
-    
-    pub fn initialize(&mut self, services: &Services) {
-        self.program = Some(program!(&services.display,
-            140 => {
-                vertex: include_str!("sprite_vs.glsl"),
-                fragment: include_str!("sprite_fs.glsl"),
-            },
-        ).unwrap());
         
-        self.vertices = Some(VertexBuffer::new(&services.display, &[
-            Vertex { position: [-0.5,  0.5], tex_coords: [0.0, 1.0] },
-            Vertex { position: [ 0.5,  0.5], tex_coords: [1.0, 1.0] },
-            Vertex { position: [-0.5, -0.5], tex_coords: [0.0, 0.0] },
-            Vertex { position: [ 0.5, -0.5], tex_coords: [1.0, 0.0] },
-        ]).unwrap());
+        // Stable, so sprites with the same key keep the order they came in
+        sprites.sort_by(|a, b| ZOrder::compare(&a.0, &b.0));
+        
+        // Every sprite's frames are in the cache's atlas, so one batch draws
+        // them all
+        let batch = SpriteBatch {
+            texture: data.services.textures.texture(),
+            instances: sprites.into_iter().map(|(_, instance)| instance).collect(),
+        };
+        data.services.renderer.submit(RenderCommand::Sprites(batch));
     }
 }
 
@@ -89,4 +83,64 @@ impl System for DrawSprites {
     type Services = Services;
 }
 
-
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use cgmath::{Matrix, Point2, Vector4};
+    use {run_frame, BuildData};
+    use assets::{AnimationSet, AtlasRegion};
+    use components::{GameComponents, Position, Sprite, Tint};
+    use systems::graphics::render::RenderCommand;
+    use test_util::{headless_world, parse_map};
+    
+    #[test]
+    fn tinted_animated_sprite() {
+        let frames = vec![
+            AtlasRegion { page: 0, uv: [0.0, 0.0, 0.25, 0.25], width: 32, height: 32 },
+            AtlasRegion { page: 1, uv: [0.5, 0.5, 0.75, 0.75], width: 32, height: 32 },
+        ];
+        let mut sprite = Sprite::new(Arc::new(AnimationSet::uniform(frames, "idle", 1.0)));
+        sprite.scale = 2.0;
+        // Just past halfway, so on the second of two frames
+        sprite.animation_time = 0.6;
+        let position = Point2::new(1.0, -0.5);
+        let tint = Vector4::new(1.0, 0.5, 0.25, 1.0);
+        
+        let mut world = headless_world(parse_map("3 3\n___\n___\n___\n"));
+        world.data.create_entity(|e: BuildData, c: &mut GameComponents| {
+            c.position.add(&e, Position::new(position));
+            c.sprite.add(&e, sprite.clone());
+            c.tint.add(&e, Tint { tint: tint });
+        });
+        // Far off screen, so culled
+        world.data.create_entity(|e: BuildData, c: &mut GameComponents| {
+            c.position.add(&e, Position::new(Point2::new(1000.0, 1000.0)));
+            c.sprite.add(&e, sprite.clone());
+        });
+        run_frame(&mut world);
+        
+        let commands = world.services.renderer.recorder().unwrap().commands();
+        let batches: Vec<_> = commands.iter().filter_map(|command| match *command {
+            RenderCommand::Sprites(ref batch) => Some(batch),
+            _ => None,
+        }).collect();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].instances.len(), 1);
+        assert_eq!((world.services.culling.sprites_drawn, world.services.culling.sprites_culled), (1, 1));
+        
+        let instance = &batches[0].instances[0];
+        assert_eq!(instance.frame, 1);
+        assert_eq!(instance.uv, [0.5, 0.5, 0.75, 0.75]);
+        assert_eq!(instance.tint, [1.0, 0.5, 0.25, 1.0]);
+        
+        let expected: [[f32; 4]; 4] = sprite.matrix(&position, &world.services.camera.matrix()).transpose().into();
+        assert_eq!(instance.matrix, expected);
+        // The camera sits at the origin and shows 5 units either side
+        // vertically, so the sprite is 2 units across and y still points up
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+        let half_width = 5.0 * 16.0 / 9.0;
+        assert!(close(instance.matrix[0][0], 2.0 / half_width));
+        assert!(close(instance.matrix[0][3], 1.0 / half_width));
+        assert!(close(instance.matrix[1][3], -0.1));
+    }
+}
@@ -1,120 +1,188 @@
-use glium::{self, Surface, Program, VertexBuffer};
-use glium::index::{NoIndices, PrimitiveType};
-use cgmath::Matrix;
+use std::sync::Arc;
+use glium::texture::Texture2dArray;
 use GameData;
 use systems::Services;
+use systems::graphics::render::{RenderCommand, TileBatch, TileInstance};
 use components::GameComponents;
+use world::tilemap::{Tilemap, Tile};
 use ecs::{System, Process};
 
-#[derive(Copy, Clone, Debug)]
-struct Vertex {
-    position: [f32; 2],
-    tex_coords: [f32; 2],
+// Which of the tilemap's layers a DrawTerrain draws
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TerrainPass {
+    // Decoration layers ordered before the main layer, drawn ahead of even
+    // the background sprites
+    Back,
+    // The main layer and decoration layers sharing its order
+    Main,
+    // Layers drawn in front of sprites
+    Front,
 }
 
-implement_vertex!(Vertex, position, tex_coords);
-
-#[derive(Copy, Clone, Debug)]
-struct Instance {
-    offset: [f32; 2],
-    tile: u32,
+impl TerrainPass {
+    fn of_order(order: i32) -> TerrainPass {
+        use world::tilemap::MAIN_LAYER_ORDER;
+        if order < MAIN_LAYER_ORDER {
+            TerrainPass::Back
+        } else if order == MAIN_LAYER_ORDER {
+            TerrainPass::Main
+        } else {
+            TerrainPass::Front
+        }
+    }
 }
 
-implement_vertex!(Instance, offset, tile);
+// A batch for each chunk of one layer. Slot 0 is the main layer and
+// decoration layers follow in the tilemap's order.
+struct LayerBatches {
+    slot: u32,
+    order: i32,
+    texture: Option<Arc<Texture2dArray>>,
+    chunks: Vec<Arc<TileBatch>>,
+}
 
 pub struct DrawTerrain {
-    program: Option<Program>,
-    vertices: Option<VertexBuffer<Vertex>>,
-    instanced: Option<VertexBuffer<Instance>>,
+    pass: TerrainPass,
+    // In draw order
+    layers: Option<Vec<LayerBatches>>,
+    // Revision of each chunk of the main layer when its batch was built
+    chunk_revisions: Vec<u32>,
+    revision: u32,
 }
 
 impl Process for DrawTerrain {
     fn process(&mut self, data: &mut GameData) {
-        if self.program.is_none() {
-            self.initialize(&data.services);
-        }
-        
-        if data.services.tilemap_changed || self.instanced.is_none() {
+        if data.services.tilemap_changed || self.layers.is_none() {
             self.setup_tiles(&data.services);
-            data.services.tilemap_changed = false;
+        } else if self.pass == TerrainPass::Main {
+            self.update_chunks(&data.services);
         }
         
-        let frame = data.services.frame.as_mut().unwrap();
-        let program = self.program.as_ref().unwrap();
-        let vertices = self.vertices.as_ref().unwrap();
-        let instanced = self.instanced.as_ref().unwrap().per_instance().unwrap();
-        let cam_matrix = data.services.camera.matrix();
-        
-        let uniforms = uniform! {
-            matrix: Into::<[[f32; 4]; 4]>::into(cam_matrix.transpose()),
-            tex: data.services.tileset.sampled().magnify_filter(
-                glium::uniforms::MagnifySamplerFilter::Nearest
-            ),
-        };
-        
-        frame.draw(
-            (vertices, instanced),
-            NoIndices(PrimitiveType::TriangleStrip),
-            program,
-            &uniforms,
-            &data.services.draw_params,
-        ).unwrap();
+        let matrix = data.services.camera.matrix();
+        let view = data.services.camera.bounds();
+        for layer in self.layers.as_ref().unwrap() {
+            for (chunk, batch) in layer.chunks.iter().enumerate() {
+                if batch.instances.is_empty() {
+                    continue;
+                }
+                if !data.services.tilemap.chunk_bounds(chunk).overlaps(&view) {
+                    data.services.culling.chunks_culled += 1;
+                    continue;
+                }
+                data.services.culling.chunks_drawn += 1;
+                data.services.renderer.submit(RenderCommand::Tiles {
+                    matrix: matrix,
+                    texture: layer.texture.clone(),
+                    batch: batch.clone(),
+                });
+            }
+        }
     }
 }
 
 impl DrawTerrain {
-    pub fn new() -> DrawTerrain {
+    pub fn new(pass: TerrainPass) -> DrawTerrain {
         DrawTerrain {
-            program: None,
-            vertices: None,
-            instanced: None,
+            pass: pass,
+            layers: None,
+            chunk_revisions: Vec::new(),
+            revision: 0,
         }
     }
     
-    pub fn initialize(&mut self, services: &Services) {
-        self.program = Some(program!(&services.display,
-            140 => {
-                vertex: include_str!("terrain_vs.glsl"),
-                fragment: include_str!("terrain_fs.glsl"),
-            },
-        ).unwrap());
-        
-        self.vertices = Some(VertexBuffer::new(&services.display, &[
-            Vertex { position: [-0.5,  0.5], tex_coords: [0.0, 0.0] },
-            Vertex { position: [ 0.5,  0.5], tex_coords: [1.0, 0.0] },
-            Vertex { position: [-0.5, -0.5], tex_coords: [0.0, 1.0] },
-            Vertex { position: [ 0.5, -0.5], tex_coords: [1.0, 1.0] },
-        ]).unwrap());
-    }
-    
+    // Rebuilds every chunk of every layer, for when the whole map changes
     pub fn setup_tiles(&mut self, services: &Services) {
-        use world::tilemap::{Tile};
+        use world::tilemap::MAIN_LAYER_ORDER;
+        
+        let tilemap = &services.tilemap;
+        let width = tilemap.width();
         
-        self.instanced = None;
+        let mut layers = Vec::new();
+        if self.pass == TerrainPass::Main {
+            self.chunk_revisions = (0..tilemap.chunk_count()).map(|chunk| {
+                tilemap.chunk_revision(chunk)
+            }).collect();
+            let chunks = (0..tilemap.chunk_count()).map(|chunk| {
+                self.build_chunk(tilemap, 0, chunk, |row, col| main_tile(tilemap, row, col))
+            }).collect();
+            layers.push(LayerBatches {
+                slot: 0,
+                order: MAIN_LAYER_ORDER,
+                texture: services.tileset.clone(),
+                chunks: chunks,
+            });
+        }
+        for (i, layer) in tilemap.layers().iter().enumerate() {
+            if TerrainPass::of_order(layer.order()) != self.pass {
+                continue;
+            }
+            let tiles = layer.tiles();
+            let slot = i as u32 + 1;
+            let chunks = (0..tilemap.chunk_count()).map(|chunk| {
+                self.build_chunk(tilemap, slot, chunk, |row, col| tiles[(row * width + col) as usize])
+            }).collect();
+            layers.push(LayerBatches {
+                slot: slot,
+                order: layer.order(),
+                texture: services.layer_tilesets.get(i).cloned(),
+                chunks: chunks,
+            });
+        }
+        // Stable, so the main layer stays ahead of layers with the same order
+        layers.sort_by(|a, b| a.order.cmp(&b.order));
         
-        let width = services.tilemap.width();
-        let height = services.tilemap.height();
-        let tiles = services.tilemap.tiles();
-        let instance_list: Vec<_> = tiles.iter().enumerate().filter(|&(_, &tile)| {
-            tile != Tile::Open
-        }).map(|(i, &tile)| {
-            let x = i as u32 % width;
-            let y = height - i as u32 / width;
-            let id = match tile {
-                Tile::Open => unreachable!(),
-                Tile::Wall => 0,
-                Tile::Breakable(_) => 1,
-            };
-            Instance {
-                offset: [x as f32, 1.0 - (y as f32)],
-                tile: id,
+        self.layers = Some(layers);
+    }
+    
+    // Rebuilds the chunks of the main layer that changed since they were built
+    fn update_chunks(&mut self, services: &Services) {
+        let tilemap = &services.tilemap;
+        for chunk in 0..tilemap.chunk_count() {
+            let revision = tilemap.chunk_revision(chunk);
+            if revision == self.chunk_revisions[chunk] {
+                continue;
+            }
+            
+            let batch = self.build_chunk(tilemap, 0, chunk, |row, col| main_tile(tilemap, row, col));
+            self.chunk_revisions[chunk] = revision;
+            if let Some(main) = self.layers.as_mut().unwrap().iter_mut().find(|layer| layer.slot == 0) {
+                main.chunks[chunk] = batch;
             }
-        }).collect();
+        }
+    }
+    
+    fn build_chunk<F>(&mut self, tilemap: &Tilemap, slot: u32, chunk: usize, tile: F) -> Arc<TileBatch>
+        where F: Fn(u32, u32) -> Option<u32> {
         
-        println!("{} tiles", instance_list.len());
+        let height = tilemap.height();
+        let (top, left, rows, cols) = tilemap.chunk_rect(chunk);
+        let mut instances = Vec::new();
+        for row in top..top + rows {
+            for col in left..left + cols {
+                if let Some(id) = tile(row, col) {
+                    instances.push(TileInstance {
+                        offset: [col as f32, 1.0 - (height - row) as f32],
+                        tile: id,
+                    });
+                }
+            }
+        }
         
-        let instanced = VertexBuffer::immutable(&services.display, &instance_list).unwrap();
-        self.instanced = Some(instanced);
+        // Backends cache batches by id and revision, so every rebuild needs a
+        // new revision even if the map was replaced
+        self.revision += 1;
+        Arc::new(TileBatch {
+            id: slot * tilemap.chunk_count() as u32 + chunk as u32,
+            revision: self.revision,
+            instances: instances,
+        })
+    }
+}
+
+fn main_tile(tilemap: &Tilemap, row: u32, col: u32) -> Option<u32> {
+    match *tilemap.tile_at(row, col) {
+        Tile::Open => None,
+        Tile::Block(id) => tilemap.tileset().def(id).texture,
     }
 }
 
@@ -1,11 +1,15 @@
 pub use self::animate::Animate;
 pub use self::camera::Camera;
+pub use self::culling::CullStats;
 pub use self::draw::{BeginDraw, EndDraw};
-pub use self::draw_sprites::DrawSprites;
-pub use self::draw_terrain::DrawTerrain;
+pub use self::draw_sprites::{DrawSprites, SpritePass};
+pub use self::draw_terrain::{DrawTerrain, TerrainPass};
+pub use self::render::{Renderer, RenderCommand};
 
 pub mod animate;
 pub mod camera;
+pub mod culling;
 pub mod draw;
 pub mod draw_sprites;
 pub mod draw_terrain;
+pub mod render;
@@ -0,0 +1,200 @@
+use std::collections::{HashMap, HashSet};
+use glium::{self, Surface, Program, VertexBuffer, Frame, DrawParameters};
+use glium::backend::glutin_backend::GlutinFacade;
+use glium::index::{NoIndices, PrimitiveType};
+use cgmath::Matrix;
+use systems::graphics::render::{Renderer, RenderCommand, SpriteInstance, TileInstance};
+
+#[derive(Copy, Clone, Debug)]
+struct Vertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+}
+
+implement_vertex!(Vertex, position, tex_coords);
+
+pub struct GliumRenderer {
+    display: GlutinFacade,
+    frame: Option<Frame>,
+    draw_params: DrawParameters<'static>,
+    
+    sprite_program: Program,
+    sprite_vertices: VertexBuffer<Vertex>,
+    terrain_program: Program,
+    terrain_vertices: VertexBuffer<Vertex>,
+    
+    // Sprite batches are written one after another into this buffer, which
+    // only grows when a frame needs more room than it has
+    sprite_instances: Option<VertexBuffer<SpriteInstance>>,
+    sprite_instances_used: usize,
+    // Uploaded tile batches by id, along with their revision. Batches that
+    // weren't drawn in a frame are dropped at its end, so replaced maps and
+    // layers don't leave their buffers behind.
+    tile_buffers: HashMap<u32, (u32, VertexBuffer<TileInstance>)>,
+    drawn_batches: HashSet<u32>,
+}
+
+impl GliumRenderer {
+    pub fn new(display: &GlutinFacade) -> GliumRenderer {
+        let sprite_program = program!(display,
+            140 => {
+                vertex: include_str!("../sprite_vs.glsl"),
+                fragment: include_str!("../sprite_fs.glsl"),
+            },
+        ).unwrap();
+        
+        let sprite_vertices = VertexBuffer::new(display, &[
+            Vertex { position: [-0.5,  0.5], tex_coords: [0.0, 1.0] },
+            Vertex { position: [ 0.5,  0.5], tex_coords: [1.0, 1.0] },
+            Vertex { position: [-0.5, -0.5], tex_coords: [0.0, 0.0] },
+            Vertex { position: [ 0.5, -0.5], tex_coords: [1.0, 0.0] },
+        ]).unwrap();
+        
+        let terrain_program = program!(display,
+            140 => {
+                vertex: include_str!("../terrain_vs.glsl"),
+                fragment: include_str!("../terrain_fs.glsl"),
+            },
+        ).unwrap();
+        
+        let terrain_vertices = VertexBuffer::new(display, &[
+            Vertex { position: [-0.5,  0.5], tex_coords: [0.0, 0.0] },
+            Vertex { position: [ 0.5,  0.5], tex_coords: [1.0, 0.0] },
+            Vertex { position: [-0.5, -0.5], tex_coords: [0.0, 1.0] },
+            Vertex { position: [ 0.5, -0.5], tex_coords: [1.0, 1.0] },
+        ]).unwrap();
+        
+        GliumRenderer {
+            display: display.clone(),
+            frame: None,
+            draw_params: DrawParameters {
+                blend: glium::Blend {
+                    color: glium::BlendingFunction::Addition {
+                        source: glium::LinearBlendingFactor::SourceAlpha,
+                        destination: glium::LinearBlendingFactor::OneMinusSourceAlpha
+                    },
+                    alpha: glium::BlendingFunction::Addition {
+                        source: glium::LinearBlendingFactor::SourceAlpha,
+                        destination: glium::LinearBlendingFactor::OneMinusSourceAlpha
+                    },
+                    constant_value: (1.0, 1.0, 1.0, 1.0)
+                },
+                
+                ..Default::default()
+            },
+            
+            sprite_program: sprite_program,
+            sprite_vertices: sprite_vertices,
+            terrain_program: terrain_program,
+            terrain_vertices: terrain_vertices,
+            
+            sprite_instances: None,
+            sprite_instances_used: 0,
+            tile_buffers: HashMap::new(),
+            drawn_batches: HashSet::new(),
+        }
+    }
+}
+
+impl Renderer for GliumRenderer {
+    fn begin_frame(&mut self) -> f32 {
+        let mut frame = self.display.draw();
+        frame.clear(None, Some((0.0, 0.0, 0.0, 0.0)), false, None, None);
+        let (width, height) = frame.get_dimensions();
+        self.frame = Some(frame);
+        self.sprite_instances_used = 0;
+        width as f32 / height as f32
+    }
+    
+    fn submit(&mut self, command: RenderCommand) {
+        let frame = self.frame.as_mut().unwrap();
+        
+        match command {
+            RenderCommand::Sprites(batch) => {
+                let texture = match batch.texture {
+                    Some(texture) => texture,
+                    None => return,
+                };
+                if batch.instances.is_empty() {
+                    return;
+                }
+                
+                // Sprites move every frame, so their instances are rewritten
+                // after the ones already drawn this frame
+                let capacity = self.sprite_instances.as_ref().map(|buffer| buffer.len()).unwrap_or(0);
+                let mut start = self.sprite_instances_used;
+                if start + batch.instances.len() > capacity {
+                    let size = (start + batch.instances.len()).next_power_of_two();
+                    self.sprite_instances = Some(VertexBuffer::empty_dynamic(&self.display, size).unwrap());
+                    start = 0;
+                }
+                let end = start + batch.instances.len();
+                self.sprite_instances_used = end;
+                let instances = self.sprite_instances.as_ref().unwrap().slice(start..end).unwrap();
+                instances.write(&batch.instances);
+                
+                let uniforms = uniform! {
+                    tex: texture.sampled().magnify_filter(
+                        glium::uniforms::MagnifySamplerFilter::Nearest
+                    ),
+                };
+                
+                frame.draw(
+                    (&self.sprite_vertices, instances.per_instance().unwrap()),
+                    NoIndices(PrimitiveType::TriangleStrip),
+                    &self.sprite_program,
+                    &uniforms,
+                    &self.draw_params,
+                ).unwrap();
+            },
+            RenderCommand::Tiles { matrix, texture, batch } => {
+                let texture = match texture {
+                    Some(texture) => texture,
+                    None => return,
+                };
+                if batch.instances.is_empty() {
+                    return;
+                }
+                
+                let stale = self.tile_buffers.get(&batch.id)
+                    .map(|&(revision, _)| revision != batch.revision)
+                    .unwrap_or(true);
+                if stale {
+                    let buffer = VertexBuffer::immutable(&self.display, &batch.instances).unwrap();
+                    self.tile_buffers.insert(batch.id, (batch.revision, buffer));
+                }
+                self.drawn_batches.insert(batch.id);
+                let instanced = self.tile_buffers[&batch.id].1.per_instance().unwrap();
+                
+                let uniforms = uniform! {
+                    matrix: Into::<[[f32; 4]; 4]>::into(matrix.transpose()),
+                    tex: texture.sampled().magnify_filter(
+                        glium::uniforms::MagnifySamplerFilter::Nearest
+                    ),
+                };
+                
+                frame.draw(
+                    (&self.terrain_vertices, instanced),
+                    NoIndices(PrimitiveType::TriangleStrip),
+                    &self.terrain_program,
+                    &uniforms,
+                    &self.draw_params,
+                ).unwrap();
+            },
+        }
+    }
+    
+    fn end_frame(&mut self) {
+        if let Some(frame) = self.frame.take() {
+            frame.finish().unwrap();
+        }
+        
+        let unused: Vec<_> = self.tile_buffers.keys().cloned().filter(|id| {
+            !self.drawn_batches.contains(id)
+        }).collect();
+        for id in unused {
+            self.tile_buffers.remove(&id);
+        }
+        self.drawn_batches.clear();
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use cgmath::Matrix4;
+use glium::texture::Texture2dArray;
+
+pub use self::glium_backend::GliumRenderer;
+pub use self::recorder::Recorder;
+
+pub mod glium_backend;
+pub mod recorder;
+
+// One sprite of a batch. `matrix` already includes the camera and is stored
+// transposed, the way the shaders multiply by it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpriteInstance {
+    pub matrix: [[f32; 4]; 4],
+    // Layer of the texture array to sample
+    pub frame: u32,
+    // Part of the layer to sample, as [left, top, right, bottom]
+    pub uv: [f32; 4],
+    pub tint: [f32; 4],
+}
+
+implement_vertex!(SpriteInstance, matrix, frame, uv, tint);
+
+// Sprites that share a texture and are drawn together, in order
+#[derive(Clone)]
+pub struct SpriteBatch {
+    pub texture: Option<Arc<Texture2dArray>>,
+    pub instances: Vec<SpriteInstance>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileInstance {
+    pub offset: [f32; 2],
+    pub tile: u32,
+}
+
+implement_vertex!(TileInstance, offset, tile);
+
+// Backends may keep GPU copies of a batch around, so a batch with the same
+// id and revision must always hold the same instances
+#[derive(Clone, Debug)]
+pub struct TileBatch {
+    pub id: u32,
+    pub revision: u32,
+    pub instances: Vec<TileInstance>,
+}
+
+#[derive(Clone)]
+pub enum RenderCommand {
+    Sprites(SpriteBatch),
+    Tiles {
+        matrix: Matrix4<f32>,
+        texture: Option<Arc<Texture2dArray>>,
+        batch: Arc<TileBatch>,
+    },
+}
+
+pub trait Renderer {
+    // Starts a frame and returns its aspect ratio
+    fn begin_frame(&mut self) -> f32;
+    fn submit(&mut self, command: RenderCommand);
+    fn end_frame(&mut self);
+    
+    fn recorder(&self) -> Option<&Recorder> {
+        None
+    }
+}
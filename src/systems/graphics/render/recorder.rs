@@ -0,0 +1,45 @@
+use systems::graphics::render::{Renderer, RenderCommand};
+
+// Keeps the commands of the last finished frame instead of drawing them
+pub struct Recorder {
+    pub aspect_ratio: f32,
+    pub frames: u64,
+    current: Vec<RenderCommand>,
+    last_frame: Vec<RenderCommand>,
+}
+
+impl Recorder {
+    pub fn new(aspect_ratio: f32) -> Recorder {
+        Recorder {
+            aspect_ratio: aspect_ratio,
+            frames: 0,
+            current: Vec::new(),
+            last_frame: Vec::new(),
+        }
+    }
+    
+    pub fn commands(&self) -> &[RenderCommand] {
+        &self.last_frame
+    }
+}
+
+impl Renderer for Recorder {
+    fn begin_frame(&mut self) -> f32 {
+        self.current.clear();
+        self.aspect_ratio
+    }
+    
+    fn submit(&mut self, command: RenderCommand) {
+        self.current.push(command);
+    }
+    
+    fn end_frame(&mut self) {
+        self.last_frame.clear();
+        self.last_frame.extend(self.current.drain(..));
+        self.frames += 1;
+    }
+    
+    fn recorder(&self) -> Option<&Recorder> {
+        Some(self)
+    }
+}
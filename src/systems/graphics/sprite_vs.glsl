@@ -1,14 +1,22 @@
 #version 140
 
-uniform mat4 matrix;
-
+// Vertex data
 in vec2 position;
 in vec2 tex_coords;
+// Instance data
+in mat4 matrix;
+in uint frame;
+in vec4 uv;
+in vec4 tint;
 
 out vec2 v_tex_coords;
+flat out uint v_frame;
+out vec4 v_tint;
 
 void main() {
     gl_Position = vec4(position, 0.0, 1.0) * matrix;
-    v_tex_coords = tex_coords;
+    v_tex_coords = mix(uv.xy, uv.zw, tex_coords);
+    v_frame = frame;
+    v_tint = tint;
 }
 
@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+use glium::glutin::{ElementState, VirtualKeyCode};
+use systems::input::bindings::{Binding, Bindings};
+use self::Action::*;
+
+pub const ACTION_COUNT: usize = 7;
+
+// How far a stick has to be pushed to hold its direction
+pub const AXIS_THRESHOLD: f32 = 0.5;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveLeft = 0,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Jump,
+    Attack,
+    Pause,
+}
+
+impl Action {
+    pub fn all() -> &'static [Action] {
+        static ALL: [Action; ACTION_COUNT] = [
+            MoveLeft, MoveRight, MoveUp, MoveDown, Jump, Attack, Pause,
+        ];
+        &ALL
+    }
+    
+    pub fn parse(name: &str) -> Option<Action> {
+        Some(match name {
+            "MoveLeft" => MoveLeft,
+            "MoveRight" => MoveRight,
+            "MoveUp" => MoveUp,
+            "MoveDown" => MoveDown,
+            "Jump" => Jump,
+            "Attack" => Attack,
+            "Pause" => Pause,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ActionState {
+    // Any bound control is down
+    pub held: bool,
+    // Went down since the last simulation step
+    pub pressed: bool,
+    // Went up since the last simulation step
+    pub released: bool,
+}
+
+pub struct Actions {
+    bindings: Bindings,
+    down: HashSet<Binding>,
+    states: [ActionState; ACTION_COUNT],
+}
+
+impl Actions {
+    pub fn new(bindings: Bindings) -> Actions {
+        Actions {
+            bindings: bindings,
+            down: HashSet::new(),
+            states: [ActionState::default(); ACTION_COUNT],
+        }
+    }
+    
+    pub fn state(&self, action: Action) -> ActionState {
+        self.states[action as usize]
+    }
+    
+    pub fn held(&self, action: Action) -> bool {
+        self.state(action).held
+    }
+    
+    pub fn pressed(&self, action: Action) -> bool {
+        self.state(action).pressed
+    }
+    
+    pub fn released(&self, action: Action) -> bool {
+        self.state(action).released
+    }
+    
+    // -1.0, 0.0 or 1.0 depending on which of the two actions are held
+    pub fn axis(&self, negative: Action, positive: Action) -> f32 {
+        let mut value = 0.0;
+        if self.held(negative) {
+            value -= 1.0;
+        }
+        if self.held(positive) {
+            value += 1.0;
+        }
+        value
+    }
+    
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+    
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.release_all();
+        self.bindings = bindings;
+    }
+    
+    pub fn clear_transitions(&mut self) {
+        for state in self.states.iter_mut() {
+            state.pressed = false;
+            state.released = false;
+        }
+    }
+    
+    pub fn key_event(&mut self, state: ElementState, key: VirtualKeyCode) {
+        self.control_event(Binding::Key(key), state == ElementState::Pressed);
+    }
+    
+    pub fn button_event(&mut self, button: u32, down: bool) {
+        self.control_event(Binding::Button(button), down);
+    }
+    
+    // Each stick axis acts as two controls, one for either direction
+    pub fn axis_event(&mut self, axis: u32, value: f32) {
+        self.control_event(Binding::AxisNegative(axis), value < -AXIS_THRESHOLD);
+        self.control_event(Binding::AxisPositive(axis), value > AXIS_THRESHOLD);
+    }
+    
+    pub fn release_all(&mut self) {
+        let down: Vec<_> = self.down.iter().cloned().collect();
+        for binding in down {
+            self.control_event(binding, false);
+        }
+    }
+    
+    fn control_event(&mut self, binding: Binding, down: bool) {
+        if down {
+            // Ignore key repeat
+            if !self.down.insert(binding) {
+                return;
+            }
+            for &action in self.bindings.actions(binding) {
+                let state = &mut self.states[action as usize];
+                if !state.held {
+                    state.held = true;
+                    state.pressed = true;
+                }
+            }
+        } else {
+            if !self.down.remove(&binding) {
+                return;
+            }
+            for &action in self.bindings.actions(binding) {
+                // Another control bound to the same action may still be down
+                let bindings = &self.bindings;
+                let still_held = self.down.iter().any(|&other| {
+                    bindings.actions(other).contains(&action)
+                });
+                
+                let state = &mut self.states[action as usize];
+                if state.held && !still_held {
+                    state.held = false;
+                    state.released = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glium::glutin::{ElementState, VirtualKeyCode};
+    use super::{Action, Actions};
+    use systems::input::bindings::Bindings;
+    
+    fn actions() -> Actions {
+        let config = "Jump = Space Pad0\nMoveLeft = Left PadAxis0-\n";
+        Actions::new(Bindings::parse(config.as_bytes()).unwrap())
+    }
+    
+    #[test]
+    fn press_and_release() {
+        let mut actions = actions();
+        actions.key_event(ElementState::Pressed, VirtualKeyCode::Space);
+        assert!(actions.pressed(Action::Jump) && actions.held(Action::Jump));
+        
+        // Key repeat and a second control don't press it again
+        actions.clear_transitions();
+        actions.key_event(ElementState::Pressed, VirtualKeyCode::Space);
+        actions.button_event(0, true);
+        assert!(!actions.pressed(Action::Jump) && actions.held(Action::Jump));
+        
+        // Held until both are let go
+        actions.key_event(ElementState::Released, VirtualKeyCode::Space);
+        assert!(actions.held(Action::Jump));
+        actions.button_event(0, false);
+        assert!(actions.released(Action::Jump) && !actions.held(Action::Jump));
+    }
+    
+    #[test]
+    fn stick_directions() {
+        let mut actions = actions();
+        actions.axis_event(0, -0.3);
+        assert!(!actions.held(Action::MoveLeft));
+        actions.axis_event(0, -0.9);
+        assert!(actions.pressed(Action::MoveLeft));
+        actions.axis_event(0, 0.9);
+        assert!(actions.released(Action::MoveLeft));
+        // Only the keyboard was rebound, so the default right stick binding stays
+        assert!(actions.held(Action::MoveRight));
+    }
+    
+    #[test]
+    fn bad_bindings() {
+        assert!(Bindings::parse("Jump = Pad\n".as_bytes()).is_err());
+        assert!(Bindings::parse("Jump = PadAxis0\n".as_bytes()).is_err());
+        assert!(Bindings::parse("Fly = Space\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+use glium::glutin::VirtualKeyCode;
+use systems::input::actions::Action;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    // Gamepad buttons and stick directions, on any connected pad
+    Button(u32),
+    AxisNegative(u32),
+    AxisPositive(u32),
+}
+
+impl Binding {
+    // Keys use glutin's names. Pad buttons are `Pad0`, `Pad1`, ... and stick
+    // directions `PadAxis0-`, `PadAxis0+`, ...
+    pub fn parse(name: &str) -> Option<Binding> {
+        if name.starts_with("PadAxis") {
+            let axis = &name[7..];
+            if axis.ends_with('-') {
+                return axis[..axis.len() - 1].parse().ok().map(Binding::AxisNegative);
+            }
+            if axis.ends_with('+') {
+                return axis[..axis.len() - 1].parse().ok().map(Binding::AxisPositive);
+            }
+            return None;
+        }
+        if name.starts_with("Pad") {
+            return name[3..].parse().ok().map(Binding::Button);
+        }
+        parse_key(name).map(Binding::Key)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    actions: HashMap<Binding, Vec<Action>>,
+}
+
+impl Bindings {
+    pub fn new() -> Bindings {
+        Bindings {
+            actions: HashMap::new(),
+        }
+    }
+    
+    pub fn bind(&mut self, binding: Binding, action: Action) {
+        let actions = self.actions.entry(binding).or_insert(Vec::new());
+        if !actions.contains(&action) {
+            actions.push(action);
+        }
+    }
+    
+    pub fn unbind_action(&mut self, action: Action) {
+        for actions in self.actions.values_mut() {
+            actions.retain(|&a| a != action);
+        }
+    }
+    
+    pub fn actions(&self, binding: Binding) -> &[Action] {
+        self.actions.get(&binding).map(|a| &a[..]).unwrap_or(&[])
+    }
+    
+    // Lines look like `Jump = Space Z Pad0`, and `#` starts a comment.
+    // Actions listed in the file replace their default bindings.
+    pub fn parse<R: BufRead>(reader: R) -> Res<Bindings> {
+        let mut bindings = Bindings::default();
+        
+        for (i, line) in reader.lines().enumerate() {
+            let line = try!(line);
+            let line_num = i as u32 + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            
+            let mut split = line.splitn(2, '=');
+            let name = split.next().unwrap().trim();
+            let controls = try!(split.next().ok_or(Error::BadLine(line_num)));
+            let action = try!(Action::parse(name).ok_or_else(|| {
+                Error::UnknownAction(line_num, name.into())
+            }));
+            
+            bindings.unbind_action(action);
+            for control in controls.split_whitespace() {
+                let binding = try!(Binding::parse(control).ok_or_else(|| {
+                    Error::UnknownControl(line_num, control.into())
+                }));
+                bindings.bind(binding, action);
+            }
+        }
+        
+        Ok(bindings)
+    }
+    
+    pub fn load(path: &str) -> Res<Bindings> {
+        use std::fs::File;
+        use std::io::BufReader;
+        let file = try!(File::open(path));
+        Bindings::parse(BufReader::new(file))
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        use glium::glutin::VirtualKeyCode as Key;
+        
+        let mut bindings = Bindings::new();
+        let defaults = [
+            (Action::MoveLeft, Key::Left),
+            (Action::MoveLeft, Key::A),
+            (Action::MoveRight, Key::Right),
+            (Action::MoveRight, Key::D),
+            (Action::MoveUp, Key::Up),
+            (Action::MoveUp, Key::W),
+            (Action::MoveDown, Key::Down),
+            (Action::MoveDown, Key::S),
+            (Action::Jump, Key::Space),
+            (Action::Jump, Key::Z),
+            (Action::Attack, Key::X),
+            (Action::Pause, Key::Escape),
+        ];
+        for &(action, key) in defaults.iter() {
+            bindings.bind(Binding::Key(key), action);
+        }
+        
+        // The usual layout: left stick, bottom face button to jump, left
+        // face button to attack and start to pause
+        let pad_defaults = [
+            (Action::MoveLeft, Binding::AxisNegative(0)),
+            (Action::MoveRight, Binding::AxisPositive(0)),
+            (Action::MoveUp, Binding::AxisNegative(1)),
+            (Action::MoveDown, Binding::AxisPositive(1)),
+            (Action::Jump, Binding::Button(0)),
+            (Action::Attack, Binding::Button(2)),
+            (Action::Pause, Binding::Button(7)),
+        ];
+        for &(action, binding) in pad_defaults.iter() {
+            bindings.bind(binding, action);
+        }
+        bindings
+    }
+}
+
+pub type Res<T> = Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    BadLine(u32),
+    UnknownAction(u32, String),
+    UnknownControl(u32, String),
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(io: io::Error) -> Error {
+        Error::Io(io)
+    }
+}
+
+fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+    use glium::glutin::VirtualKeyCode::*;
+    Some(match name {
+        "A" => A, "B" => B, "C" => C, "D" => D, "E" => E, "F" => F, "G" => G,
+        "H" => H, "I" => I, "J" => J, "K" => K, "L" => L, "M" => M, "N" => N,
+        "O" => O, "P" => P, "Q" => Q, "R" => R, "S" => S, "T" => T, "U" => U,
+        "V" => V, "W" => W, "X" => X, "Y" => Y, "Z" => Z,
+        "0" => Key0, "1" => Key1, "2" => Key2, "3" => Key3, "4" => Key4,
+        "5" => Key5, "6" => Key6, "7" => Key7, "8" => Key8, "9" => Key9,
+        "Left" => Left, "Right" => Right, "Up" => Up, "Down" => Down,
+        "Space" => Space, "Return" => Return, "Escape" => Escape, "Tab" => Tab,
+        "Back" => Back,
+        "LShift" => LShift, "RShift" => RShift,
+        "LControl" => LControl, "RControl" => RControl,
+        "LAlt" => LAlt, "RAlt" => RAlt,
+        _ => return None,
+    })
+}
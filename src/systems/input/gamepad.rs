@@ -0,0 +1,90 @@
+use std::fs::File;
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+// glutin doesn't report gamepads, so they're read straight from the Linux
+// joystick devices. Pads have to be plugged in before the game starts, and
+// on other platforms there just aren't any.
+const MAX_PADS: u32 = 4;
+
+// Event types from linux/joystick.h
+const JS_EVENT_BUTTON: u8 = 0x01;
+const JS_EVENT_AXIS: u8 = 0x02;
+// Set on the made up events describing the state when the device was opened
+const JS_EVENT_INIT: u8 = 0x80;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PadEvent {
+    Button(u32, bool),
+    // From -1.0 to 1.0, with negative values left or up
+    Axis(u32, f32),
+}
+
+pub struct Gamepads {
+    events: Receiver<PadEvent>,
+}
+
+impl Gamepads {
+    // Starts a thread reading each pad that's connected
+    pub fn open() -> Gamepads {
+        let (sender, receiver) = mpsc::channel();
+        for i in 0..MAX_PADS {
+            if let Ok(device) = File::open(format!("/dev/input/js{}", i)) {
+                let sender = sender.clone();
+                thread::spawn(move || read_events(device, sender));
+            }
+        }
+        Gamepads {
+            events: receiver,
+        }
+    }
+    
+    // Everything that happened since the last poll
+    pub fn poll(&self) -> Vec<PadEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => events.push(event),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => return events,
+            }
+        }
+    }
+}
+
+// Runs until the pad is unplugged or the game stops listening
+fn read_events<R: Read>(mut device: R, sender: Sender<PadEvent>) {
+    let mut event = [0; 8];
+    while device.read_exact(&mut event).is_ok() {
+        if let Some(event) = parse_event(&event) {
+            if sender.send(event).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// A struct js_event: a u32 timestamp, an i16 value, then the type and number
+fn parse_event(event: &[u8; 8]) -> Option<PadEvent> {
+    let value = (event[4] as u16 | (event[5] as u16) << 8) as i16;
+    let number = event[7] as u32;
+    match event[6] & !JS_EVENT_INIT {
+        JS_EVENT_BUTTON => Some(PadEvent::Button(number, value != 0)),
+        JS_EVENT_AXIS => Some(PadEvent::Axis(number, value as f32 / 32767.0)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_event, PadEvent};
+    
+    #[test]
+    fn events() {
+        assert_eq!(parse_event(&[0, 0, 0, 0, 1, 0, 0x01, 3]), Some(PadEvent::Button(3, true)));
+        assert_eq!(parse_event(&[0, 0, 0, 0, 0, 0, 0x81, 3]), Some(PadEvent::Button(3, false)));
+        // -32767
+        assert_eq!(parse_event(&[0, 0, 0, 0, 0x01, 0x80, 0x02, 1]), Some(PadEvent::Axis(1, -1.0)));
+        assert_eq!(parse_event(&[0, 0, 0, 0, 0, 0, 0x04, 0]), None);
+    }
+}
@@ -0,0 +1,62 @@
+use GameData;
+use systems::Services;
+use components::GameComponents;
+use ecs::{System, Process};
+use glium::glutin::Event;
+
+pub use self::actions::{Action, Actions, ActionState};
+pub use self::bindings::{Binding, Bindings};
+pub use self::gamepad::{Gamepads, PadEvent};
+
+pub mod actions;
+pub mod bindings;
+pub mod gamepad;
+
+pub struct Input {
+    gamepads: Gamepads,
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input {
+            gamepads: Gamepads::open(),
+        }
+    }
+}
+
+impl Process for Input {
+    fn process(&mut self, data: &mut GameData) {
+        let display = match data.services.display {
+            Some(ref display) => display,
+            None => return,
+        };
+        
+        for event in self.gamepads.poll() {
+            match event {
+                PadEvent::Button(button, down) => data.services.actions.button_event(button, down),
+                PadEvent::Axis(axis, value) => data.services.actions.axis_event(axis, value),
+            }
+        }
+        
+        for event in display.poll_events() {
+            match event {
+                Event::Closed => {
+                    data.services.running = false;
+                },
+                Event::KeyboardInput(state, _, Some(key)) => {
+                    data.services.actions.key_event(state, key);
+                },
+                // Keys released while unfocused would otherwise stay held
+                Event::Focused(false) => {
+                    data.services.actions.release_all();
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+impl System for Input {
+    type Components = GameComponents;
+    type Services = Services;
+}
@@ -1,59 +1,195 @@
 use std::sync::Arc;
 use ecs;
+use ecs::Process;
 use ecs::system::entity::EntitySystem;
 use glium::backend::glutin_backend::GlutinFacade;
-use glium::{self, Frame, DrawParameters};
+use glium;
 use world::tilemap::Tilemap;
+use level::Prefabs;
+use assets::TextureCache;
 use components::GameComponents;
+use GameData;
 
+pub mod events;
 pub mod gameplay;
 pub mod graphics;
 pub mod physics;
 pub mod input;
 pub mod time;
 
-systems! {
-    struct GameSystems<GameComponents, Services> {
-        // Time
-        time: time::Time = time::Time,
+// Lists every system once, in the order it runs. Frame systems run once per
+// rendered frame and simulation systems once per fixed step, both from
+// run_frame; World::update only runs the draw systems.
+macro_rules! game_systems {
+    {
+        frame: { $($frame:ident: $frame_ty:ty = $frame_new:expr,)+ }
+        simulation: { $($sim:ident: $sim_ty:ty = $sim_new:expr,)+ }
+        draw: { $($draw:ident: $draw_ty:ty = $draw_new:expr,)+ }
+    } => {
+        systems! {
+            struct GameSystems<GameComponents, Services> {
+                active: {
+                    $($draw: $draw_ty = $draw_new,)+
+                },
+                passive: {
+                    $($frame: $frame_ty = $frame_new,)+
+                    $($sim: $sim_ty = $sim_new,)+
+                }
+            }
+        }
         
-        // Input
-        input: input::Input = input::Input,
+        impl GameSystems {
+            // Time and input are sampled once per rendered frame
+            pub fn begin_frame(&mut self, data: &mut GameData) {
+                $(self.$frame.process(data);)+
+            }
+            
+            // Advances the game by one fixed timestep
+            pub fn simulate(&mut self, data: &mut GameData) {
+                $(self.$sim.process(data);)+
+                data.services.actions.clear_transitions();
+            }
+        }
+    }
+}
+
+game_systems! {
+    frame: {
+        time: time::Time = time::Time,
+        input: input::Input = input::Input::new(),
+    }
+    
+    simulation: {
+        flush_events: events::FlushEvents = events::FlushEvents,
         
         // Gameplay
+        player_control: EntitySystem<gameplay::PlayerControl> = EntitySystem::new(
+            gameplay::PlayerControl,
+            aspect!(<GameComponents> all: [player_controller, position, velocity, collider]),
+        ),
+        crawler_ai: EntitySystem<gameplay::CrawlerAi> = EntitySystem::new(
+            gameplay::CrawlerAi,
+            aspect!(<GameComponents> all: [crawler, position, velocity, collider]),
+        ),
+        triggers: EntitySystem<gameplay::Triggers> = EntitySystem::new(
+            gameplay::Triggers,
+            aspect!(<GameComponents> all: [trigger, position]),
+        ),
+        tile_hazards: gameplay::TileHazards = gameplay::TileHazards,
+        update_stats: gameplay::UpdateStats = gameplay::UpdateStats,
+        
+        // Physics
+        save_positions: EntitySystem<physics::SavePositions> = EntitySystem::new(
+            physics::SavePositions,
+            aspect!(<GameComponents> all: [position]),
+        ),
+        movement: EntitySystem<physics::Movement> = EntitySystem::new(
+            physics::Movement,
+            aspect!(<GameComponents> all: [position, velocity]),
+        ),
+        collision: EntitySystem<physics::Collision> = EntitySystem::new(
+            physics::Collision,
+            aspect!(<GameComponents> all: [position, velocity, collider]),
+        ),
+        
+        // Collision responses
+        break_blocks: EntitySystem<gameplay::BreakBlocks> = EntitySystem::new(
+            gameplay::BreakBlocks,
+            aspect!(<GameComponents> all: [player_controller, position, collider]),
+        ),
+    }
+    
+    draw: {
         camera_follow: EntitySystem<gameplay::CameraFollow> = EntitySystem::new(
             gameplay::CameraFollow,
             aspect!(<GameComponents> all: [camera_follow, position]),
         ),
-        
-        // TODO: Physics
-        
-        // Graphics
+        player_animation: EntitySystem<gameplay::PlayerAnimation> = EntitySystem::new(
+            gameplay::PlayerAnimation,
+            aspect!(<GameComponents> all: [player_controller, collider, velocity, sprite]),
+        ),
         animate: EntitySystem<graphics::Animate> = EntitySystem::new(
             graphics::Animate,
             aspect!(<GameComponents> all: [sprite]),
         ),
         begin_draw: graphics::BeginDraw = graphics::BeginDraw,
-        draw_terrain: graphics::DrawTerrain = graphics::DrawTerrain::new(),
+        draw_backdrop: graphics::DrawTerrain = graphics::DrawTerrain::new(
+            graphics::TerrainPass::Back,
+        ),
+        draw_background: EntitySystem<graphics::DrawSprites> = EntitySystem::new(
+            graphics::DrawSprites::new(graphics::SpritePass::Background),
+            aspect!(<GameComponents> all: [sprite, position]),
+        ),
+        draw_terrain: graphics::DrawTerrain = graphics::DrawTerrain::new(
+            graphics::TerrainPass::Main,
+        ),
         draw_sprites: EntitySystem<graphics::DrawSprites> = EntitySystem::new(
-            graphics::DrawSprites::new(),
+            graphics::DrawSprites::new(graphics::SpritePass::Front),
             aspect!(<GameComponents> all: [sprite, position]),
         ),
+        draw_foreground: graphics::DrawTerrain = graphics::DrawTerrain::new(
+            graphics::TerrainPass::Front,
+        ),
         end_draw: graphics::EndDraw = graphics::EndDraw,
     }
 }
 
 pub struct Services {
+    // Length of a simulation step
     pub delta_time: f64,
+    // Length of the last rendered frame
+    pub frame_time: f64,
+    pub timestep: time::Timestep,
     pub running_time: f64,
     pub running: bool,
+    pub actions: input::Actions,
+    pub events: events::Events,
+    pub player: Option<gameplay::PlayerBody>,
+    pub stats: gameplay::PlayerStats,
+    // Set when the whole tilemap is replaced; edits are tracked per chunk
     pub tilemap_changed: bool,
     pub tilemap: Tilemap,
-    pub tileset: Arc<glium::texture::Texture2dArray>,
-    pub display: GlutinFacade,
-    pub frame: Option<Frame>,
+    pub prefabs: Prefabs,
+    pub textures: TextureCache,
+    // Graphics resources are None when running headless
+    pub tileset: Option<Arc<glium::texture::Texture2dArray>>,
+    // Tilesets for the tilemap's decoration layers, in the same order
+    pub layer_tilesets: Vec<Arc<glium::texture::Texture2dArray>>,
+    pub display: Option<GlutinFacade>,
+    pub renderer: Box<graphics::Renderer>,
     pub camera: graphics::Camera,
-    pub draw_params: DrawParameters<'static>,
+    pub culling: graphics::CullStats,
+}
+
+impl Services {
+    // Services for playing a level without a window's resources. Callers with
+    // a display fill in the graphics resources and bindings afterwards.
+    pub fn new(
+        tilemap: Tilemap, prefabs: Prefabs, textures: TextureCache, renderer: Box<graphics::Renderer>
+    ) -> Services {
+        
+        Services {
+            delta_time: 0.0,
+            frame_time: 0.0,
+            timestep: time::Timestep::new(120.0),
+            running_time: -1.0,
+            running: true,
+            actions: input::Actions::new(Default::default()),
+            events: events::Events::new(),
+            player: None,
+            stats: gameplay::PlayerStats::new(),
+            tilemap_changed: true,
+            tilemap: tilemap,
+            prefabs: prefabs,
+            textures: textures,
+            tileset: None,
+            layer_tilesets: Vec::new(),
+            display: None,
+            renderer: renderer,
+            camera: graphics::Camera::new(),
+            culling: graphics::CullStats::new(),
+        }
+    }
 }
 
 impl ecs::ServiceManager for Services {}
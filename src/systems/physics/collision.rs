@@ -0,0 +1,183 @@
+use GameData;
+use systems::Services;
+use components::GameComponents;
+use world::tilemap::Tilemap;
+use ecs::{System, EntityIter};
+use ecs::system::entity::EntityProcess;
+use cgmath::{Point2, Vector2};
+
+// Keeps boxes that are exactly touching a tile from counting as inside it
+const EPSILON: f32 = 0.0001;
+
+pub struct Collision;
+
+impl EntityProcess for Collision {
+    fn process(&mut self, entities: EntityIter<GameComponents>, data: &mut GameData) {
+        let dt = data.services.delta_time as f32;
+        let tilemap = &data.services.tilemap;
+        
+        for e in entities {
+            let mut position = data.components.position[e].position;
+            let mut velocity = data.components.velocity[e].velocity;
+            let mut collider = data.components.collider[e];
+            collider.clear_contacts();
+            
+            // Resolve each axis separately so entities slide along surfaces
+            let dx = velocity.x * dt;
+            match sweep_x(tilemap, position, collider.half_size, dx) {
+                Some((x, _)) => {
+                    position.x = x;
+                    velocity.x = 0.0;
+                    if dx > 0.0 {
+                        collider.wall_right = true;
+                    } else {
+                        collider.wall_left = true;
+                    }
+                },
+                None => position.x += dx,
+            }
+            
+            let dy = velocity.y * dt;
+            match sweep_y(tilemap, position, collider.half_size, dy) {
+                Some((y, row)) => {
+                    position.y = y;
+                    velocity.y = 0.0;
+                    if dy < 0.0 {
+                        collider.grounded = true;
+                    } else {
+                        collider.ceiling = true;
+                        collider.ceiling_tile = struck_tile(
+                            tilemap, position, collider.half_size, row
+                        );
+                    }
+                },
+                None => position.y += dy,
+            }
+            
+            // Something resting on the ground with no vertical speed is still grounded
+            if dy == 0.0 {
+                let probe = sweep_y(tilemap, position, collider.half_size, -2.0 * EPSILON);
+                collider.grounded = probe.is_some();
+            }
+            
+            data.components.position[e].position = position;
+            data.components.velocity[e].velocity = velocity;
+            data.components.collider[e] = collider;
+        }
+    }
+}
+
+impl System for Collision {
+    type Components = GameComponents;
+    type Services = Services;
+}
+
+// The sides of the level are walls, but the sky and pits are open
+fn solid(tilemap: &Tilemap, row: i32, col: i32) -> bool {
+    if col < 0 || col >= tilemap.width() as i32 {
+        return true;
+    }
+    tilemap.get_filled(row, col).unwrap_or(false)
+}
+
+// Solid tiles, plus one-way tiles that are only solid from above
+fn lands_on(tilemap: &Tilemap, row: i32, col: i32) -> bool {
+    if solid(tilemap, row, col) {
+        return true;
+    }
+    tilemap.in_bounds(row, col) && tilemap.one_way_at(row as u32, col as u32)
+}
+
+// Returns the clamped x position and the column that was hit if moving by dx
+// would run into a solid tile
+fn sweep_x(
+    tilemap: &Tilemap, pos: Point2<f32>, half: Vector2<f32>, dx: f32
+) -> Option<(f32, i32)> {
+    let top = tilemap.row_at(pos.y + half.y - EPSILON);
+    let bottom = tilemap.row_at(pos.y - half.y + EPSILON);
+    let blocked = |col| (top..bottom + 1).any(|row| solid(tilemap, row, col));
+    
+    if dx > 0.0 {
+        let edge = pos.x + half.x;
+        let first = tilemap.col_at(edge - EPSILON) + 1;
+        let last = tilemap.col_at(edge + dx);
+        for col in first..last + 1 {
+            if blocked(col) {
+                return Some((col as f32 - 0.5 - half.x, col));
+            }
+        }
+    } else if dx < 0.0 {
+        let edge = pos.x - half.x;
+        let first = tilemap.col_at(edge + EPSILON) - 1;
+        let last = tilemap.col_at(edge + dx);
+        for col in (last..first + 1).rev() {
+            if blocked(col) {
+                return Some((col as f32 + 0.5 + half.x, col));
+            }
+        }
+    }
+    
+    None
+}
+
+// Returns the clamped y position and the row that was hit if moving by dy
+// would run into a solid tile
+fn sweep_y(
+    tilemap: &Tilemap, pos: Point2<f32>, half: Vector2<f32>, dy: f32
+) -> Option<(f32, i32)> {
+    let left = tilemap.col_at(pos.x - half.x + EPSILON);
+    let right = tilemap.col_at(pos.x + half.x - EPSILON);
+    let blocked = |row| (left..right + 1).any(|col| solid(tilemap, row, col));
+    
+    if dy < 0.0 {
+        // Every row checked here starts out below the box, so one-way tiles
+        // can only be landed on from above
+        let landed = |row| (left..right + 1).any(|col| lands_on(tilemap, row, col));
+        let edge = pos.y - half.y;
+        let first = tilemap.row_at(edge + EPSILON) + 1;
+        let last = tilemap.row_at(edge + dy);
+        for row in first..last + 1 {
+            if landed(row) {
+                return Some((tilemap.row_y(row) + 0.5 + half.y, row));
+            }
+        }
+    } else if dy > 0.0 {
+        let edge = pos.y + half.y;
+        let first = tilemap.row_at(edge - EPSILON) - 1;
+        let last = tilemap.row_at(edge + dy);
+        for row in (last..first + 1).rev() {
+            if blocked(row) {
+                return Some((tilemap.row_y(row) - 0.5 - half.y, row));
+            }
+        }
+    }
+    
+    None
+}
+
+// The solid tile in a row that is closest to the middle of the box
+fn struck_tile(
+    tilemap: &Tilemap, pos: Point2<f32>, half: Vector2<f32>, row: i32
+) -> Option<(u32, u32)> {
+    if row < 0 || row >= tilemap.height() as i32 {
+        return None;
+    }
+    
+    let left = tilemap.col_at(pos.x - half.x + EPSILON);
+    let right = tilemap.col_at(pos.x + half.x - EPSILON);
+    let mut best: Option<i32> = None;
+    for col in left..right + 1 {
+        if !tilemap.in_bounds(row, col) || !solid(tilemap, row, col) {
+            continue;
+        }
+        let closer = match best {
+            Some(b) => (col as f32 - pos.x).abs() < (b as f32 - pos.x).abs(),
+            None => true,
+        };
+        if closer {
+            best = Some(col);
+        }
+    }
+    
+    best.map(|col| (row as u32, col as u32))
+}
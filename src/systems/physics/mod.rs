@@ -1,3 +1,7 @@
+pub use self::collision::Collision;
 pub use self::movement::Movement;
+pub use self::save_positions::SavePositions;
 
+pub mod collision;
 pub mod movement;
+pub mod save_positions;
@@ -1,2 +1,38 @@
+use GameData;
+use systems::Services;
+use components::GameComponents;
+use ecs::{System, EntityIter};
+use ecs::system::entity::EntityProcess;
+
 pub struct Movement;
 
+impl EntityProcess for Movement {
+    fn process(&mut self, entities: EntityIter<GameComponents>, data: &mut GameData) {
+        let dt = data.services.delta_time as f32;
+        for e in entities {
+            let mut velocity = data.components.velocity[e].velocity;
+            
+            if let Some(gravity) = data.components.gravity.get(&e) {
+                velocity = velocity + gravity.gravity * dt;
+            }
+            
+            if let Some(drag) = data.components.drag.get(&e) {
+                // Implicit form so large timesteps can't flip the direction
+                velocity = velocity / (1.0 + drag.drag * dt);
+            }
+            
+            data.components.velocity[e].velocity = velocity;
+            
+            // Entities with a collider are moved by the collision system
+            if !data.components.collider.has(&e) {
+                let position = data.components.position[e].position;
+                data.components.position[e].position = position + velocity * dt;
+            }
+        }
+    }
+}
+
+impl System for Movement {
+    type Components = GameComponents;
+    type Services = Services;
+}
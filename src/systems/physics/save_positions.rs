@@ -0,0 +1,21 @@
+use GameData;
+use systems::Services;
+use components::GameComponents;
+use ecs::{System, EntityIter};
+use ecs::system::entity::EntityProcess;
+
+pub struct SavePositions;
+
+impl EntityProcess for SavePositions {
+    fn process(&mut self, entities: EntityIter<GameComponents>, data: &mut GameData) {
+        for e in entities {
+            let position = &mut data.components.position[e];
+            position.previous = position.position;
+        }
+    }
+}
+
+impl System for SavePositions {
+    type Components = GameComponents;
+    type Services = Services;
+}
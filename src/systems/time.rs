@@ -10,14 +10,18 @@ impl Process for Time {
     fn process(&mut self, data: &mut GameData) {
         let old_time = data.services.running_time;
         let new_time = time::precise_time_s();
-        let delta = if old_time > 0.0 {
+        let delta = if let Some(frame_time) = data.services.timestep.fixed_frame_time {
+            frame_time
+        } else if old_time > 0.0 {
             new_time - old_time
         } else {
             0.0
         };
         
-        data.services.delta_time = delta;
+        data.services.frame_time = delta;
         data.services.running_time = new_time;
+        data.services.timestep.advance(delta);
+        data.services.delta_time = data.services.timestep.step;
     }
 }
 
@@ -25,3 +29,44 @@ impl System for Time {
     type Components = GameComponents;
     type Services = Services;
 }
+
+pub struct Timestep {
+    // Seconds of game time simulated by each step
+    pub step: f64,
+    // Frames longer than this are cut short instead of caught up on, so one
+    // hitch can't leave the simulation permanently behind
+    pub max_frame_time: f64,
+    // Pretend every frame took this long instead of measuring it
+    pub fixed_frame_time: Option<f64>,
+    accumulator: f64,
+}
+
+impl Timestep {
+    pub fn new(steps_per_second: f64) -> Timestep {
+        Timestep {
+            step: 1.0 / steps_per_second,
+            max_frame_time: 0.25,
+            fixed_frame_time: None,
+            accumulator: 0.0,
+        }
+    }
+    
+    pub fn advance(&mut self, frame_time: f64) {
+        self.accumulator += frame_time.min(self.max_frame_time);
+    }
+    
+    // Consumes one step of accumulated time if there is enough of it
+    pub fn next_step(&mut self) -> bool {
+        if self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            true
+        } else {
+            false
+        }
+    }
+    
+    // How far between the last step and the next one the current frame is
+    pub fn alpha(&self) -> f64 {
+        self.accumulator / self.step
+    }
+}
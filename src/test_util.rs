@@ -0,0 +1,32 @@
+// Fixtures shared by the tests of several modules
+use GameWorld;
+use assets::TextureCache;
+use level::{self, Prefabs};
+use systems::Services;
+use systems::graphics::render::Recorder;
+use world::tilemap::Tilemap;
+use world::tileset::Tileset;
+
+// Open air and a solid wall, enough for most test maps
+pub const TILESET: &'static str = "texture wall.png\nopen _\ntile # wall texture=0 solid\n";
+
+pub fn tileset() -> Tileset {
+    Tileset::parse(TILESET.as_bytes()).unwrap()
+}
+
+pub fn parse_map(map: &str) -> Tilemap {
+    Tilemap::parse_text_map(map.as_bytes(), &tileset()).unwrap()
+}
+
+// A world playing the map without a window, recording what it draws and stepping
+// the simulation once per frame so results don't depend on how fast the tests run
+pub fn headless_world(tilemap: Tilemap) -> GameWorld {
+    let mut textures = TextureCache::new(None);
+    let prefabs = Prefabs::load_default(&mut textures).unwrap();
+    let renderer = Box::new(Recorder::new(16.0 / 9.0));
+    let mut world = GameWorld::with_services(Services::new(tilemap, prefabs, textures, renderer));
+    let step = world.services.timestep.step;
+    world.services.timestep.fixed_frame_time = Some(step);
+    level::spawn_entities(&mut world.data);
+    world
+}
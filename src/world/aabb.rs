@@ -0,0 +1,37 @@
+use cgmath::{Point2, Vector2};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub center: Point2<f32>,
+    pub half_size: Vector2<f32>,
+}
+
+impl Aabb {
+    pub fn new(center: Point2<f32>, half_size: Vector2<f32>) -> Aabb {
+        Aabb {
+            center: center,
+            half_size: half_size,
+        }
+    }
+    
+    pub fn left(&self) -> f32 {
+        self.center.x - self.half_size.x
+    }
+    
+    pub fn right(&self) -> f32 {
+        self.center.x + self.half_size.x
+    }
+    
+    pub fn bottom(&self) -> f32 {
+        self.center.y - self.half_size.y
+    }
+    
+    pub fn top(&self) -> f32 {
+        self.center.y + self.half_size.y
+    }
+    
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.left() < other.right() && other.left() < self.right() &&
+        self.bottom() < other.top() && other.bottom() < self.top()
+    }
+}
@@ -0,0 +1,428 @@
+use std::char;
+use std::cmp;
+use std::fs::File;
+use std::io::{self, Read, Write, BufReader, BufWriter};
+use std::u32;
+use world::entities::EntityType;
+use world::tilemap::{Tilemap, Tile, TileLayer, InputTile, Spawn, Error, Res, tile_count};
+use world::tileset::Tileset;
+
+// Compact binary levels. After the magic number come
+//
+//     version, width, height
+//     palette: count, then the symbol of each kind of tile used
+//     main layer: runs of (length, palette index + 1, or 0 for open)
+//     decoration layers: count, then name, order, tileset paths and runs of
+//         (length, tile index + 1, or 0 for empty)
+//     spawns: count, then entity id, row, col and properties
+//     metadata: count, then keys and values
+//     FNV-1a checksum of everything before it, as 4 little endian bytes
+//
+// Numbers are LEB128 varints, signed ones zigzagged first. Strings are their
+// length followed by UTF-8. Tiles are stored by symbol rather than id so that
+// levels survive tiles being added to the tileset.
+
+pub const MAGIC: &'static [u8] = b"ECSL";
+pub const VERSION: u32 = 1;
+
+// Tiles decoded across all of a level's layers. Runs let a few bytes stand for
+// any number of tiles, so this keeps a corrupt file from asking for gigabytes.
+const MAX_TILES: usize = 1 << 24;
+
+pub fn save<W: Write>(tilemap: &Tilemap, writer: &mut W) -> io::Result<()> {
+    let mut out = MAGIC.to_vec();
+    put_uint(&mut out, VERSION);
+    put_uint(&mut out, tilemap.width());
+    put_uint(&mut out, tilemap.height());
+    
+    let mut palette: Vec<char> = Vec::new();
+    let main: Vec<_> = tilemap.iter().map(|(_, _, &tile)| {
+        match tile {
+            Tile::Open => 0,
+            Tile::Block(id) => {
+                let symbol = tilemap.tileset().def(id).symbol;
+                let found = palette.iter().position(|&s| s == symbol);
+                let index = match found {
+                    Some(index) => index,
+                    None => {
+                        palette.push(symbol);
+                        palette.len() - 1
+                    }
+                };
+                index as u32 + 1
+            },
+        }
+    }).collect();
+    put_uint(&mut out, palette.len() as u32);
+    for &symbol in &palette {
+        put_uint(&mut out, symbol as u32);
+    }
+    put_runs(&mut out, &main);
+    
+    put_uint(&mut out, tilemap.layers().len() as u32);
+    for layer in tilemap.layers() {
+        put_str(&mut out, layer.name());
+        put_int(&mut out, layer.order());
+        put_uint(&mut out, layer.tileset().len() as u32);
+        for path in layer.tileset() {
+            put_str(&mut out, path);
+        }
+        let tiles: Vec<_> = layer.tiles().iter().map(|tile| tile.map(|t| t + 1).unwrap_or(0)).collect();
+        put_runs(&mut out, &tiles);
+    }
+    
+    put_uint(&mut out, tilemap.spawns().len() as u32);
+    for spawn in tilemap.spawns() {
+        put_uint(&mut out, spawn.entity as u32);
+        put_uint(&mut out, spawn.row);
+        put_uint(&mut out, spawn.col);
+        put_uint(&mut out, spawn.properties.len() as u32);
+        for (key, value) in &spawn.properties {
+            put_str(&mut out, key);
+            put_str(&mut out, value);
+        }
+    }
+    
+    put_uint(&mut out, tilemap.metadata().len() as u32);
+    for (key, value) in tilemap.metadata() {
+        put_str(&mut out, key);
+        put_str(&mut out, value);
+    }
+    
+    let sum = checksum(&out);
+    out.extend_from_slice(&[sum as u8, (sum >> 8) as u8, (sum >> 16) as u8, (sum >> 24) as u8]);
+    writer.write_all(&out)
+}
+
+pub fn load<R: Read>(reader: &mut R, tileset: &Tileset) -> Res<Tilemap> {
+    let mut bytes = Vec::new();
+    try!(reader.read_to_end(&mut bytes));
+    if bytes.len() < MAGIC.len() + 4 || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(Error::BadBinary("not a binary level".into()));
+    }
+    
+    let (body, sum) = bytes.split_at(bytes.len() - 4);
+    let expected = sum.iter().rev().fold(0, |acc, &b| acc << 8 | b as u32);
+    let found = checksum(body);
+    if found != expected {
+        return Err(Error::BadChecksum { expected: expected, found: found });
+    }
+    
+    let mut input = Input { bytes: body, pos: MAGIC.len(), tiles_left: MAX_TILES };
+    let version = try!(input.uint());
+    if version != VERSION {
+        return Err(Error::UnsupportedBinaryVersion(version));
+    }
+    let width = try!(input.uint());
+    let height = try!(input.uint());
+    let size = try!(tile_count(width, height).ok_or_else(|| {
+        Error::BadBinary(format!("{}x{} map is too large", width, height))
+    })) as usize;
+    
+    let mut palette = Vec::new();
+    for _ in 0..try!(input.uint()) {
+        let symbol = try!(input.uint());
+        palette.push(try!(char::from_u32(symbol).ok_or_else(|| {
+            Error::BadBinary(format!("{} isn't a tile symbol", symbol))
+        })));
+    }
+    let main: Res<Vec<_>> = try!(input.runs(size)).into_iter().map(|tile| {
+        match tile {
+            0 => Ok(InputTile::Open),
+            n => palette.get(n as usize - 1).map(|&symbol| InputTile::Tile(symbol)).ok_or_else(|| {
+                Error::BadBinary(format!("{} isn't in the palette", n - 1))
+            }),
+        }
+    }).collect();
+    let mut tilemap = try!(Tilemap::parse_input(width, height, &try!(main), tileset));
+    
+    for _ in 0..try!(input.uint()) {
+        let name = try!(input.string());
+        let order = try!(input.int());
+        let mut paths = Vec::new();
+        for _ in 0..try!(input.uint()) {
+            paths.push(try!(input.string()));
+        }
+        let tiles = try!(input.runs(size)).into_iter().map(|tile| {
+            if tile == 0 { None } else { Some(tile - 1) }
+        }).collect();
+        try!(tilemap.add_layer(TileLayer::new(name, order, paths, tiles)));
+    }
+    
+    for _ in 0..try!(input.uint()) {
+        let id = try!(input.uint());
+        let entity = try!(EntityType::parse(id as u8).ok().and_then(|entity| {
+            if entity as u32 == id { Some(entity) } else { None }
+        }).ok_or_else(|| Error::BadBinary(format!("unknown entity {}", id))));
+        let row = try!(input.uint());
+        let col = try!(input.uint());
+        if row >= height || col >= width {
+            return Err(Error::BadBinary(format!("spawn at {}, {} is outside the map", row, col)));
+        }
+        let mut spawn = Spawn {
+            entity: entity,
+            row: row,
+            col: col,
+            properties: Default::default(),
+        };
+        for _ in 0..try!(input.uint()) {
+            let key = try!(input.string());
+            spawn.properties.insert(key, try!(input.string()));
+        }
+        tilemap.add_spawn(spawn);
+    }
+    
+    for _ in 0..try!(input.uint()) {
+        let key = try!(input.string());
+        let value = try!(input.string());
+        tilemap.metadata_mut().insert(key, value);
+    }
+    
+    if input.pos != body.len() {
+        return Err(Error::BadBinary("trailing data".into()));
+    }
+    Ok(tilemap)
+}
+
+pub fn load_file(path: &str, tileset: &Tileset) -> Res<Tilemap> {
+    let file = try!(File::open(path).map_err(|e| Error::in_file(path, Error::Io(e))));
+    load(&mut BufReader::new(file), tileset).map_err(|e| Error::in_file(path, e))
+}
+
+pub fn save_file(tilemap: &Tilemap, path: &str) -> Res<()> {
+    let file = try!(File::create(path).map_err(|e| Error::in_file(path, Error::Io(e))));
+    save(tilemap, &mut BufWriter::new(file)).map_err(|e| Error::in_file(path, Error::Io(e)))
+}
+
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash: u32, &b| (hash ^ b as u32).wrapping_mul(0x01000193))
+}
+
+fn put_uint(out: &mut Vec<u8>, mut n: u32) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn put_int(out: &mut Vec<u8>, n: i32) {
+    put_uint(out, ((n << 1) ^ (n >> 31)) as u32);
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) {
+    put_uint(out, s.len() as u32);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn put_runs(out: &mut Vec<u8>, values: &[u32]) {
+    let mut i = 0;
+    while i < values.len() {
+        let value = values[i];
+        let length = values[i..].iter().take_while(|&&v| v == value).count();
+        put_uint(out, length as u32);
+        put_uint(out, value);
+        i += length;
+    }
+}
+
+struct Input<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    tiles_left: usize,
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, count: usize) -> Res<&'a [u8]> {
+        if self.bytes.len() - self.pos < count {
+            return Err(Error::BadBinary("level is cut short".into()));
+        }
+        let taken = &self.bytes[self.pos..self.pos + count];
+        self.pos += count;
+        Ok(taken)
+    }
+    
+    fn uint(&mut self) -> Res<u32> {
+        let mut n = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = try!(self.take(1))[0];
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            if shift > 28 {
+                return Err(Error::BadBinary("number is too long".into()));
+            }
+        }
+        if n > u32::MAX as u64 {
+            return Err(Error::BadBinary("number is too large".into()));
+        }
+        Ok(n as u32)
+    }
+    
+    fn int(&mut self) -> Res<i32> {
+        let n = try!(self.uint());
+        Ok((n >> 1) as i32 ^ -((n & 1) as i32))
+    }
+    
+    fn string(&mut self) -> Res<String> {
+        let length = try!(self.uint()) as usize;
+        let bytes = try!(self.take(length));
+        String::from_utf8(bytes.to_vec()).map_err(|_| {
+            Error::BadBinary("string isn't UTF-8".into())
+        })
+    }
+    
+    fn runs(&mut self, count: usize) -> Res<Vec<u32>> {
+        if count > self.tiles_left {
+            return Err(Error::BadBinary("level has too many tiles".into()));
+        }
+        self.tiles_left -= count;
+        
+        // Every run takes at least two bytes, so short input can't be many runs
+        let mut values = Vec::with_capacity(cmp::min(count, self.bytes.len() - self.pos));
+        while values.len() < count {
+            let length = try!(self.uint()) as usize;
+            let value = try!(self.uint());
+            if length == 0 || values.len() + length > count {
+                return Err(Error::BadBinary("tile runs don't fit the map".into()));
+            }
+            values.extend((0..length).map(|_| value));
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{save, load, checksum, put_uint, MAGIC, VERSION};
+    use world::entities::EntityType;
+    use world::tilemap::{Tilemap, TileLayer, InputTile, Spawn, Error};
+    use world::tileset::Tileset;
+    use test_util::TILESET;
+    
+    fn tileset() -> Tileset {
+        let tiles = format!("{}tile - ledge texture=0 one_way\n", TILESET);
+        Tileset::parse(tiles.as_bytes()).unwrap()
+    }
+    
+    // Five wide and three high, with a layer, spawns and metadata
+    fn tilemap(tileset: &Tileset) -> Tilemap {
+        let tiles = "#___#\
+                     _--__\
+                     #####".chars().map(|c| InputTile::Tile(c)).collect::<Vec<_>>();
+        let mut tilemap = Tilemap::parse_input(5, 3, &tiles, tileset).unwrap();
+        
+        let layer_tiles = (0..15).map(|i| if i % 4 == 0 { None } else { Some(i % 2) }).collect();
+        tilemap.add_layer(TileLayer::new(
+            "clouds".into(), -2, vec!["a.png".into(), "b.png".into()], layer_tiles
+        )).unwrap();
+        
+        let mut player = Spawn {
+            entity: EntityType::Player,
+            row: 1,
+            col: 0,
+            properties: Default::default(),
+        };
+        player.properties.insert("lives".into(), "3".into());
+        tilemap.add_spawn(player);
+        tilemap.add_spawn(Spawn {
+            entity: EntityType::Goal,
+            row: 1,
+            col: 4,
+            properties: Default::default(),
+        });
+        tilemap.metadata_mut().insert("name".into(), "Level ünïcode".into());
+        tilemap
+    }
+    
+    // A level with the given body and a valid checksum
+    fn with_checksum(mut bytes: Vec<u8>) -> Vec<u8> {
+        let sum = checksum(&bytes);
+        bytes.extend_from_slice(&[sum as u8, (sum >> 8) as u8, (sum >> 16) as u8, (sum >> 24) as u8]);
+        bytes
+    }
+    
+    #[test]
+    fn round_trip() {
+        let tileset = tileset();
+        let tilemap = tilemap(&tileset);
+        let mut bytes = Vec::new();
+        save(&tilemap, &mut bytes).unwrap();
+        
+        let loaded = load(&mut &bytes[..], &tileset).unwrap();
+        assert_eq!(loaded, tilemap);
+        assert_eq!(loaded.width(), 5);
+        assert_eq!(loaded.height(), 3);
+    }
+    
+    #[test]
+    fn corrupted_checksum() {
+        let tileset = tileset();
+        let mut bytes = Vec::new();
+        save(&tilemap(&tileset), &mut bytes).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        
+        match load(&mut &bytes[..], &tileset) {
+            Err(Error::BadChecksum { .. }) => {},
+            other => panic!("expected a checksum error, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn corrupted_body() {
+        let tileset = tileset();
+        let mut bytes = Vec::new();
+        save(&tilemap(&tileset), &mut bytes).unwrap();
+        bytes[MAGIC.len() + 2] ^= 0x40;
+        
+        match load(&mut &bytes[..], &tileset) {
+            Err(Error::BadChecksum { .. }) => {},
+            other => panic!("expected a checksum error, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn truncated_varint() {
+        // The version's continuation bit promises another byte that never comes
+        let mut body = MAGIC.to_vec();
+        body.push(0x81);
+        let bytes = with_checksum(body);
+        
+        match load(&mut &bytes[..], &tileset()) {
+            Err(Error::BadBinary(_)) => {},
+            other => panic!("expected a truncation error, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn oversized_header() {
+        // An empty palette and one run of open tiles covering the map
+        let header = |width: u32, height: u32| {
+            let mut body = MAGIC.to_vec();
+            put_uint(&mut body, VERSION);
+            put_uint(&mut body, width);
+            put_uint(&mut body, height);
+            put_uint(&mut body, 0);
+            put_uint(&mut body, width.wrapping_mul(height));
+            put_uint(&mut body, 0);
+            with_checksum(body)
+        };
+        let too_large = |bytes: Vec<u8>| match load(&mut &bytes[..], &tileset()) {
+            Err(Error::BadBinary(_)) => {},
+            other => panic!("expected the size to be rejected, got {:?}", other),
+        };
+        
+        // Overflows the tile count
+        too_large(header(0xffffffff, 0xffffffff));
+        // Fits in a u32 and takes a few bytes, but would still need gigabytes
+        too_large(header(60000, 60000));
+    }
+}
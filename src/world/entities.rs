@@ -1,6 +1,6 @@
 use self::EntityType::*;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable)]
 pub enum EntityType {
     Goal = 0,
     Checkpoint,
@@ -18,4 +18,14 @@ impl EntityType {
             _ => return Err(id),
         })
     }
+    
+    pub fn parse_name(name: &str) -> Option<EntityType> {
+        Some(match &name.to_lowercase()[..] {
+            "goal" => Goal,
+            "checkpoint" => Checkpoint,
+            "player" => Player,
+            "crawler" => Crawler,
+            _ => return None,
+        })
+    }
 }
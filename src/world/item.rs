@@ -1,6 +1,7 @@
 use self::Item::*;
+use std::str::FromStr;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, RustcEncodable, RustcDecodable)]
 pub enum Item {
     Empty,
     Coins(u32),
@@ -8,13 +9,13 @@ pub enum Item {
 }
 
 impl Item {
-    pub fn parse(value: char) -> Result<Item, char> {
-        Ok(match value {
-            '-' => Empty,
-            'c' => Coins(1),
-            'C' => Coins(3),
-            '$' => Coins(10),
-            '+' => ExtraLife,
+    // Items are written `empty`, `life` or `coins:N`
+    pub fn parse(value: &str) -> Result<Item, &str> {
+        let mut split = value.splitn(2, ':');
+        Ok(match (split.next().unwrap(), split.next()) {
+            ("empty", None) => Empty,
+            ("life", None) => ExtraLife,
+            ("coins", Some(count)) => Coins(try!(u32::from_str(count).map_err(|_| value))),
             _ => return Err(value),
         })
     }
@@ -1,3 +1,9 @@
+pub mod aabb;
+pub mod binary;
 pub mod entities;
 pub mod item;
+pub mod query;
+pub mod tiled;
 pub mod tilemap;
+pub mod tileset;
+pub mod tmx;
@@ -0,0 +1,338 @@
+use std::cmp;
+use std::f32;
+use cgmath::{Point2, Vector2, EuclideanVector};
+use world::aabb::Aabb;
+use world::tilemap::{Tilemap, Tile};
+
+// Lookups that take coordinates from world space or from outside the map.
+//
+// Tiles are centered on integer coordinates and span half a unit each way.
+// Rows count down from the top of the map but world space points up, so the
+// bottom row sits at y = 0. Rows and columns are signed here so that points
+// off the edges of the map still have coordinates.
+impl Tilemap {
+    pub fn in_bounds(&self, row: i32, col: i32) -> bool {
+        row >= 0 && col >= 0 && row < self.height() as i32 && col < self.width() as i32
+    }
+    
+    pub fn get(&self, row: i32, col: i32) -> Option<&Tile> {
+        if self.in_bounds(row, col) {
+            Some(self.tile_at(row as u32, col as u32))
+        } else {
+            None
+        }
+    }
+    
+    pub fn get_filled(&self, row: i32, col: i32) -> Option<bool> {
+        if self.in_bounds(row, col) {
+            Some(self.filled_at(row as u32, col as u32))
+        } else {
+            None
+        }
+    }
+    
+    pub fn col_at(&self, x: f32) -> i32 {
+        (x + 0.5).floor() as i32
+    }
+    
+    pub fn row_at(&self, y: f32) -> i32 {
+        self.height() as i32 - 1 - (y + 0.5).floor() as i32
+    }
+    
+    pub fn col_x(&self, col: i32) -> f32 {
+        col as f32
+    }
+    
+    pub fn row_y(&self, row: i32) -> f32 {
+        (self.height() as i32 - 1 - row) as f32
+    }
+    
+    // World space center of a tile
+    pub fn tile_position(&self, row: u32, col: u32) -> Point2<f32> {
+        Point2::new(self.col_x(col as i32), self.row_y(row as i32))
+    }
+    
+    // The (row, col) of the tile containing a world space point
+    pub fn tile_coords(&self, point: Point2<f32>) -> Option<(u32, u32)> {
+        let row = self.row_at(point.y);
+        let col = self.col_at(point.x);
+        if self.in_bounds(row, col) {
+            Some((row as u32, col as u32))
+        } else {
+            None
+        }
+    }
+    
+    // The bounds of a tile in world space
+    pub fn tile_bounds(&self, row: i32, col: i32) -> Aabb {
+        Aabb::new(Point2::new(self.col_x(col), self.row_y(row)), Vector2::new(0.5, 0.5))
+    }
+    
+    // The area covered by every tile of a chunk
+    pub fn chunk_bounds(&self, chunk: usize) -> Aabb {
+        let (row, col, rows, cols) = self.chunk_rect(chunk);
+        let x = self.col_x(col as i32) + (cols - 1) as f32 / 2.0;
+        let y = self.row_y(row as i32) - (rows - 1) as f32 / 2.0;
+        Aabb::new(Point2::new(x, y), Vector2::new(cols as f32 / 2.0, rows as f32 / 2.0))
+    }
+    
+    // Every tile along with its (row, col), row by row from the top
+    pub fn iter(&self) -> TileIter {
+        TileIter {
+            tilemap: self,
+            next: 0,
+        }
+    }
+    
+    // The tiles on the map that a box overlaps or touches
+    pub fn tiles_overlapping(&self, bounds: &Aabb) -> TileRect {
+        let top = cmp::max(self.row_at(bounds.top()), 0);
+        let bottom = cmp::min(self.row_at(bounds.bottom()), self.height() as i32 - 1);
+        let left = cmp::max(self.col_at(bounds.left()), 0);
+        let right = cmp::min(self.col_at(bounds.right()), self.width() as i32 - 1);
+        TileRect {
+            left: left,
+            right: right,
+            bottom: bottom,
+            row: top,
+            col: left,
+        }
+    }
+    
+    pub fn solid_overlapping(&self, bounds: &Aabb) -> bool {
+        self.tiles_overlapping(bounds).any(|(row, col)| self.filled_at(row, col))
+    }
+    
+    // Walks the grid from `origin` towards `direction` and returns the first
+    // solid tile within `max_distance`. Everything off the map is open.
+    pub fn raycast(
+        &self, origin: Point2<f32>, direction: Vector2<f32>, max_distance: f32
+    ) -> Option<RayHit> {
+        if direction.x == 0.0 && direction.y == 0.0 {
+            return None;
+        }
+        let direction = direction.normalize();
+        
+        // Columns go the same way as x, rows the opposite way to y
+        let mut col = self.col_at(origin.x);
+        let mut row = self.row_at(origin.y);
+        let step_col = if direction.x > 0.0 { 1 } else { -1 };
+        let step_row = if direction.y > 0.0 { -1 } else { 1 };
+        
+        // Distance along the ray to the next column and row boundaries, and
+        // between boundaries
+        let (mut next_col, col_delta) = if direction.x != 0.0 {
+            let edge = self.col_x(col) + 0.5 * step_col as f32;
+            ((edge - origin.x) / direction.x, 1.0 / direction.x.abs())
+        } else {
+            (f32::INFINITY, f32::INFINITY)
+        };
+        let (mut next_row, row_delta) = if direction.y != 0.0 {
+            let edge = self.row_y(row) - 0.5 * step_row as f32;
+            ((edge - origin.y) / direction.y, 1.0 / direction.y.abs())
+        } else {
+            (f32::INFINITY, f32::INFINITY)
+        };
+        
+        // Starting inside a solid tile hits it straight away
+        let mut distance = 0.0;
+        let mut normal = Vector2::new(0.0, 0.0);
+        loop {
+            if self.get_filled(row, col) == Some(true) {
+                return Some(RayHit {
+                    row: row as u32,
+                    col: col as u32,
+                    point: origin + direction * distance,
+                    normal: normal,
+                    distance: distance,
+                });
+            }
+            
+            // Once off the map and not heading back, nothing can be hit
+            let width = self.width() as i32;
+            let height = self.height() as i32;
+            if (col < 0 && direction.x <= 0.0) || (col >= width && direction.x >= 0.0) ||
+                (row < 0 && direction.y >= 0.0) || (row >= height && direction.y <= 0.0) {
+                return None;
+            }
+            
+            if next_col < next_row {
+                distance = next_col;
+                next_col += col_delta;
+                col += step_col;
+                normal = Vector2::new(-step_col as f32, 0.0);
+            } else {
+                distance = next_row;
+                next_row += row_delta;
+                row += step_row;
+                normal = Vector2::new(0.0, step_row as f32);
+            }
+            if distance > max_distance {
+                return None;
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    pub row: u32,
+    pub col: u32,
+    // Where the ray entered the tile
+    pub point: Point2<f32>,
+    // Points back out of the face that was hit, or is zero if the ray
+    // started inside the tile
+    pub normal: Vector2<f32>,
+    pub distance: f32,
+}
+
+pub struct TileIter<'a> {
+    tilemap: &'a Tilemap,
+    next: u32,
+}
+
+impl<'a> Iterator for TileIter<'a> {
+    type Item = (u32, u32, &'a Tile);
+    
+    fn next(&mut self) -> Option<(u32, u32, &'a Tile)> {
+        let width = self.tilemap.width();
+        if self.next >= width * self.tilemap.height() {
+            return None;
+        }
+        let (row, col) = (self.next / width, self.next % width);
+        self.next += 1;
+        Some((row, col, self.tilemap.tile_at(row, col)))
+    }
+}
+
+// Iterates over the (row, col) of each tile in a rectangle
+pub struct TileRect {
+    left: i32,
+    right: i32,
+    bottom: i32,
+    row: i32,
+    col: i32,
+}
+
+impl Iterator for TileRect {
+    type Item = (u32, u32);
+    
+    fn next(&mut self) -> Option<(u32, u32)> {
+        if self.col > self.right {
+            self.col = self.left;
+            self.row += 1;
+        }
+        if self.row > self.bottom || self.left > self.right {
+            return None;
+        }
+        let tile = (self.row as u32, self.col as u32);
+        self.col += 1;
+        Some(tile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Point2, Vector2};
+    use world::aabb::Aabb;
+    use world::tilemap::{Tile, Tilemap};
+    use test_util::parse_map;
+    
+    // The wall at row 2, col 2 is centered on (2, 1) and the floor is at y = 0
+    fn tilemap() -> Tilemap {
+        parse_map("4 5\n_____\n_____\n__#__\n#####\n")
+    }
+    
+    fn near(a: Point2<f32>, b: Point2<f32>) -> bool {
+        (a.x - b.x).abs() < 1e-4 && (a.y - b.y).abs() < 1e-4
+    }
+    
+    #[test]
+    fn raycast_normals() {
+        let map = tilemap();
+        
+        let hit = map.raycast(Point2::new(0.0, 1.0), Vector2::new(1.0, 0.0), 10.0).unwrap();
+        assert_eq!((hit.row, hit.col, hit.normal), (2, 2, Vector2::new(-1.0, 0.0)));
+        assert_eq!((hit.point, hit.distance), (Point2::new(1.5, 1.0), 1.5));
+        
+        let hit = map.raycast(Point2::new(4.0, 1.0), Vector2::new(-1.0, 0.0), 10.0).unwrap();
+        assert_eq!((hit.row, hit.col, hit.normal), (2, 2, Vector2::new(1.0, 0.0)));
+        
+        // Straight down onto the wall's top face
+        let hit = map.raycast(Point2::new(2.0, 3.0), Vector2::new(0.0, -1.0), 10.0).unwrap();
+        assert_eq!((hit.row, hit.col, hit.normal), (2, 2, Vector2::new(0.0, 1.0)));
+        assert_eq!((hit.point, hit.distance), (Point2::new(2.0, 1.5), 1.5));
+        
+        // Diagonally down and right, crossing columns and rows alternately
+        let hit = map.raycast(Point2::new(0.2, 3.0), Vector2::new(1.0, -1.0), 10.0).unwrap();
+        assert_eq!((hit.row, hit.col, hit.normal), (2, 2, Vector2::new(0.0, 1.0)));
+        assert!(near(hit.point, Point2::new(1.7, 1.5)), "hit at {:?}", hit.point);
+        assert!((hit.distance - 1.5 * 2.0f32.sqrt()).abs() < 1e-4);
+    }
+    
+    #[test]
+    fn raycast_from_inside_a_wall() {
+        let hit = tilemap().raycast(Point2::new(2.1, 0.9), Vector2::new(0.0, 1.0), 10.0).unwrap();
+        assert_eq!((hit.row, hit.col), (2, 2));
+        assert_eq!((hit.point, hit.normal, hit.distance), (Point2::new(2.1, 0.9), Vector2::new(0.0, 0.0), 0.0));
+    }
+    
+    #[test]
+    fn raycast_off_the_map() {
+        let map = tilemap();
+        
+        // Leaves through the right edge without hitting anything
+        assert_eq!(map.raycast(Point2::new(0.0, 2.0), Vector2::new(1.0, 0.0), 100.0), None);
+        // Starts off the map heading away from it
+        assert_eq!(map.raycast(Point2::new(-3.0, 1.0), Vector2::new(-1.0, 0.0), 100.0), None);
+        
+        // Comes in from the left, crossing open tiles before the wall
+        let hit = map.raycast(Point2::new(-3.0, 1.0), Vector2::new(1.0, 0.0), 100.0).unwrap();
+        assert_eq!((hit.row, hit.col, hit.distance), (2, 2, 4.5));
+        
+        // Comes up from below and hits the floor's underside
+        let hit = map.raycast(Point2::new(4.0, -2.0), Vector2::new(0.0, 1.0), 100.0).unwrap();
+        assert_eq!((hit.row, hit.col, hit.normal), (3, 4, Vector2::new(0.0, -1.0)));
+        assert_eq!((hit.point, hit.distance), (Point2::new(4.0, -0.5), 1.5));
+    }
+    
+    #[test]
+    fn raycast_zero_and_max_distance() {
+        let map = tilemap();
+        assert_eq!(map.raycast(Point2::new(2.0, 1.0), Vector2::new(0.0, 0.0), 10.0), None);
+        
+        let origin = Point2::new(0.0, 1.0);
+        let right = Vector2::new(1.0, 0.0);
+        assert_eq!(map.raycast(origin, right, 1.4), None);
+        assert!(map.raycast(origin, right, 1.5).is_some());
+        // The direction doesn't need to be normalized
+        assert_eq!(map.raycast(origin, right * 5.0, 1.5), map.raycast(origin, right, 1.5));
+    }
+    
+    #[test]
+    fn tiles_overlapping() {
+        let map = tilemap();
+        let around_wall = Aabb::new(Point2::new(1.5, 1.5), Vector2::new(0.4, 0.4));
+        assert_eq!(map.tiles_overlapping(&around_wall).collect::<Vec<_>>(),
+                   vec![(1, 1), (1, 2), (2, 1), (2, 2)]);
+        assert!(map.solid_overlapping(&around_wall));
+        
+        // Boxes hanging off the map are clipped to it
+        let corner = Aabb::new(Point2::new(-1.0, 3.5), Vector2::new(0.6, 0.6));
+        assert_eq!(map.tiles_overlapping(&corner).collect::<Vec<_>>(), vec![(0, 0)]);
+        assert!(!map.solid_overlapping(&corner));
+        
+        let outside = Aabb::new(Point2::new(-5.0, -5.0), Vector2::new(0.4, 0.4));
+        assert_eq!(map.tiles_overlapping(&outside).count(), 0);
+    }
+    
+    #[test]
+    fn iter() {
+        let map = parse_map("2 3\n_#_\n#__\n");
+        let tiles: Vec<_> = map.iter().map(|(row, col, tile)| (row, col, *tile != Tile::Open)).collect();
+        assert_eq!(tiles, vec![
+            (0, 0, false), (0, 1, true), (0, 2, false),
+            (1, 0, true), (1, 1, false), (1, 2, false),
+        ]);
+    }
+}
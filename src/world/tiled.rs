@@ -0,0 +1,375 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+use rustc_serialize::json::Json;
+use world::entities::EntityType;
+use world::tilemap::{Tilemap, TileLayer, InputTile, Spawn, Error, Res, tile_count};
+use world::tileset::Tileset;
+use world::tmx;
+
+// Imports maps made in Tiled (http://www.mapeditor.org/).
+//
+// The tile layer named "main", or the first one if none is, becomes the
+// collision layer. Its tiles say which of our tile definitions they stand for
+// with a "tile" property, falling back on their type. Other tile layers become
+// decoration layers, ordered by where they sit relative to the main one unless
+// they have an "order" property, and need tilesets made of separate images.
+// Objects spawn the entity named by their type, and keep their properties.
+
+// Tiled keeps flip flags in the top bits of each gid
+const GID_MASK: u32 = 0x1fffffff;
+
+struct TiledTile {
+    // Name of our tile definition it stands for
+    def: Option<String>,
+    image: Option<String>,
+}
+
+pub fn load_tiled(path: &str, tileset: &Tileset) -> Res<Tilemap> {
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    let map = try!(read_document(Path::new(path)).map_err(|e| Error::in_file(path, e)));
+    from_json(&map, dir, tileset).map_err(|e| Error::in_file(path, e))
+}
+
+// Image paths and external tilesets are looked up relative to `dir`
+pub fn parse_tiled_json<R: Read>(reader: &mut R, dir: &Path, tileset: &Tileset) -> Res<Tilemap> {
+    let map = try!(Json::from_reader(reader).map_err(Error::Json));
+    from_json(&map, dir, tileset)
+}
+
+pub fn parse_tmx<R: Read>(reader: &mut R, dir: &Path, tileset: &Tileset) -> Res<Tilemap> {
+    let map = try!(tmx::parse(reader));
+    from_json(&map, dir, tileset)
+}
+
+// TMX and TSX files are read into the same shape as Tiled's JSON
+fn read_document(path: &Path) -> Res<Json> {
+    let mut file = BufReader::new(try!(File::open(path)));
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("tmx") | Some("tsx") => tmx::parse(&mut file),
+        _ => Json::from_reader(&mut file).map_err(Error::Json),
+    }
+}
+
+fn from_json(map: &Json, dir: &Path, tileset: &Tileset) -> Res<Tilemap> {
+    let width = try!(uint(map, "width"));
+    let height = try!(uint(map, "height"));
+    let tile_width = try!(uint(map, "tilewidth")) as f64;
+    let tile_height = try!(uint(map, "tileheight")) as f64;
+    
+    if let Some(orientation) = map.find("orientation").and_then(Json::as_string) {
+        if orientation != "orthogonal" {
+            return Err(Error::UnsupportedTiled(format!("{} maps", orientation)));
+        }
+    }
+    if map.find("infinite").and_then(Json::as_boolean) == Some(true) {
+        return Err(Error::UnsupportedTiled("infinite maps".into()));
+    }
+    
+    let mut tiles = BTreeMap::new();
+    for set in try!(array(map, "tilesets")) {
+        let first_gid = try!(uint(set, "firstgid"));
+        match set.find("source").and_then(Json::as_string) {
+            Some(source) => {
+                let path = dir.join(source);
+                let path_name = path.to_string_lossy().into_owned();
+                let external = try!(read_document(&path).map_err(|e| Error::in_file(&path_name, e)));
+                let set_dir = path.parent().unwrap_or(dir);
+                try!(read_tileset(&external, set_dir, first_gid, &mut tiles).map_err(|e| {
+                    Error::in_file(&path_name, e)
+                }));
+            },
+            None => try!(read_tileset(set, dir, first_gid, &mut tiles)),
+        }
+    }
+    
+    let mut layers = Vec::new();
+    try!(collect_layers(try!(array(map, "layers")), &mut layers));
+    let tile_layers: Vec<_> = layers.iter().cloned().filter(|layer| {
+        layer.find("type").and_then(Json::as_string) == Some("tilelayer")
+    }).collect();
+    if tile_layers.is_empty() {
+        return Err(Error::BadTiled("there are no tile layers".into()));
+    }
+    let main = tile_layers.iter().position(|layer| {
+        layer.find("name").and_then(Json::as_string) == Some("main") ||
+            properties(layer).get("main").map(|main| &main[..]) == Some("true")
+    }).unwrap_or(0);
+    
+    // The main layer goes through the same path as text maps
+    let input: Res<Vec<_>> = try!(layer_data(tile_layers[main], width, height)).iter().map(|&gid| {
+        if gid == 0 {
+            return Ok(InputTile::Open);
+        }
+        let name = try!(tiles.get(&gid).and_then(|tile: &TiledTile| tile.def.as_ref()).ok_or(
+            Error::UnknownGid(gid)
+        ));
+        let id = try!(tileset.find(name).ok_or_else(|| Error::UnknownTileName(name.clone())));
+        Ok(InputTile::Tile(tileset.def(id).symbol))
+    }).collect();
+    let mut tilemap = try!(Tilemap::parse_input(width, height, &try!(input), tileset));
+    
+    // Decoration layers all share one tileset of every image in the map
+    let images: Vec<_> = tiles.iter().filter_map(|(&gid, tile)| {
+        tile.image.clone().map(|image| (gid, image))
+    }).collect();
+    let image_paths: Vec<_> = images.iter().map(|&(_, ref image)| image.clone()).collect();
+    for (i, layer) in tile_layers.iter().enumerate() {
+        if i == main {
+            continue;
+        }
+        
+        let layer_tiles: Res<Vec<_>> = try!(layer_data(layer, width, height)).iter().map(|&gid| {
+            if gid == 0 {
+                return Ok(None);
+            }
+            images.iter().position(|&(g, _)| g == gid).map(|index| Some(index as u32)).ok_or(
+                Error::UnsupportedTiled("decoration tiles without their own image".into())
+            )
+        }).collect();
+        
+        let order = match properties(layer).get("order") {
+            Some(order) => try!(i32::from_str(order).map_err(|_| {
+                Error::BadTiled(format!("layer order {} isn't a number", order))
+            })),
+            None => i as i32 - main as i32,
+        };
+        let name = try!(string(layer, "name"));
+        try!(tilemap.add_layer(TileLayer::new(name.into(), order, image_paths.clone(), try!(layer_tiles))));
+    }
+    
+    // Spawns
+    for layer in layers.iter().filter(|layer| {
+        layer.find("type").and_then(Json::as_string) == Some("objectgroup")
+    }) {
+        for object in try!(array(layer, "objects")) {
+            let kind = object.find("type").or(object.find("class"))
+                .and_then(Json::as_string)
+                .unwrap_or("");
+            let entity = try!(EntityType::parse_name(kind).ok_or_else(|| {
+                Error::UnknownObject(kind.into())
+            }));
+            
+            let x = try!(float(object, "x"));
+            let y = try!(float(object, "y"));
+            let w = object.find("width").and_then(Json::as_f64).unwrap_or(0.0);
+            let h = object.find("height").and_then(Json::as_f64).unwrap_or(0.0);
+            // Tile objects are positioned by their bottom left corner
+            let center_y = if object.find("gid").is_some() { y - h / 2.0 } else { y + h / 2.0 };
+            let col = ((x + w / 2.0) / tile_width).floor();
+            let row = (center_y / tile_height).floor();
+            if col < 0.0 || row < 0.0 || col >= width as f64 || row >= height as f64 {
+                return Err(Error::BadTiled(format!("{} object at {}, {} is outside the map", kind, x, y)));
+            }
+            
+            tilemap.add_spawn(Spawn {
+                entity: entity,
+                row: row as u32,
+                col: col as u32,
+                properties: properties(object),
+            });
+        }
+    }
+    
+    tilemap.metadata_mut().extend(properties(map));
+    Ok(tilemap)
+}
+
+fn read_tileset(set: &Json, dir: &Path, first_gid: u32, tiles: &mut BTreeMap<u32, TiledTile>) -> Res<()> {
+    let set_tiles = match set.find("tiles") {
+        Some(set_tiles) => try!(set_tiles.as_array().ok_or_else(|| {
+            Error::UnsupportedTiled("tilesets from before Tiled 1.0".into())
+        })),
+        None => return Ok(()),
+    };
+    
+    for tile in set_tiles {
+        let id = try!(uint(tile, "id"));
+        let def = properties(tile).remove("tile").or_else(|| {
+            tile.find("type").or(tile.find("class")).and_then(Json::as_string).map(|t| t.into())
+        });
+        let image = tile.find("image").and_then(Json::as_string).map(|image| {
+            dir.join(image).to_string_lossy().into_owned()
+        });
+        tiles.insert(first_gid + id, TiledTile {
+            def: def,
+            image: image,
+        });
+    }
+    Ok(())
+}
+
+fn collect_layers<'a>(layers: &'a [Json], out: &mut Vec<&'a Json>) -> Res<()> {
+    for layer in layers {
+        if layer.find("type").and_then(Json::as_string) == Some("group") {
+            try!(collect_layers(try!(array(layer, "layers")), out));
+        } else {
+            out.push(layer);
+        }
+    }
+    Ok(())
+}
+
+fn layer_data(layer: &Json, width: u32, height: u32) -> Res<Vec<u32>> {
+    let data = match layer.find("data") {
+        Some(&Json::Array(ref data)) => data,
+        Some(_) => return Err(Error::UnsupportedTiled("encoded layer data".into())),
+        None => return Err(Error::BadTiled("tile layer has no data".into())),
+    };
+    if tile_count(width, height) != Some(data.len() as u32) {
+        return Err(Error::BadMapSize {
+            width: width,
+            height: height,
+            found: data.len() as u32,
+        });
+    }
+    data.iter().map(|gid| {
+        gid.as_u64().map(|gid| gid as u32 & GID_MASK).ok_or_else(|| {
+            Error::BadTiled(format!("{} isn't a tile", gid))
+        })
+    }).collect()
+}
+
+// Tiled 1.2 and later write properties as a list of names, types and values,
+// earlier versions as an object
+fn properties(json: &Json) -> BTreeMap<String, String> {
+    let mut properties = BTreeMap::new();
+    match json.find("properties") {
+        Some(&Json::Array(ref list)) => {
+            for property in list {
+                let name = property.find("name").and_then(Json::as_string);
+                if let (Some(name), Some(value)) = (name, property.find("value")) {
+                    properties.insert(name.into(), json_string(value));
+                }
+            }
+        },
+        Some(&Json::Object(ref object)) => {
+            for (name, value) in object {
+                properties.insert(name.clone(), json_string(value));
+            }
+        },
+        _ => {}
+    }
+    properties
+}
+
+fn json_string(value: &Json) -> String {
+    match *value {
+        Json::String(ref s) => s.clone(),
+        ref other => other.to_string(),
+    }
+}
+
+fn missing(key: &str) -> Error {
+    Error::BadTiled(format!("missing or invalid {}", key))
+}
+
+fn uint(json: &Json, key: &str) -> Res<u32> {
+    json.find(key).and_then(Json::as_u64).map(|n| n as u32).ok_or_else(|| missing(key))
+}
+
+fn float(json: &Json, key: &str) -> Res<f64> {
+    json.find(key).and_then(Json::as_f64).ok_or_else(|| missing(key))
+}
+
+fn string<'a>(json: &'a Json, key: &str) -> Res<&'a str> {
+    json.find(key).and_then(Json::as_string).ok_or_else(|| missing(key))
+}
+
+fn array<'a>(json: &'a Json, key: &str) -> Res<&'a [Json]> {
+    json.find(key).and_then(Json::as_array).map(|a| &a[..]).ok_or_else(|| missing(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::{load_tiled, parse_tiled_json};
+    use world::entities::EntityType;
+    use world::tilemap::{Tile, Error};
+    use world::tileset::Tileset;
+    
+    const SAMPLE: &'static str = "assets/levels/tiled_sample.json";
+    // The same map saved as TMX, with its tileset in a separate TSX file
+    const SAMPLE_TMX: &'static str = "assets/levels/tiled_sample.tmx";
+    
+    fn tileset() -> Tileset {
+        Tileset::load("assets/tilesets/basic/tiles.cfg").unwrap()
+    }
+    
+    fn tile_name(tileset: &Tileset, tile: &Tile) -> Option<String> {
+        match *tile {
+            Tile::Open => None,
+            Tile::Block(id) => Some(tileset.def(id).name.clone()),
+        }
+    }
+    
+    #[test]
+    fn main_layer() {
+        let tileset = tileset();
+        let tilemap = load_tiled(SAMPLE, &tileset).unwrap();
+        assert_eq!(tilemap.width(), 12);
+        assert_eq!(tilemap.height(), 6);
+        
+        // Through the type, a "tile" property and the type again
+        let row: Vec<_> = (3..8).map(|col| tile_name(&tileset, tilemap.tile_at(2, col))).collect();
+        assert_eq!(row, vec![None, Some("block".into()), Some("coin_block".into()), Some("block".into()), None]);
+        for col in 0..12 {
+            assert_eq!(tile_name(&tileset, tilemap.tile_at(5, col)), Some("wall".into()));
+        }
+        assert_eq!(tile_name(&tileset, tilemap.tile_at(3, 5)), None);
+        assert_eq!(tilemap.metadata().get("name").map(|name| &name[..]), Some("Tiled sample"));
+    }
+    
+    #[test]
+    fn decoration_layers() {
+        let tilemap = load_tiled(SAMPLE, &tileset()).unwrap();
+        let layers: Vec<_> = tilemap.layers().iter().map(|layer| (layer.name(), layer.order())).collect();
+        assert_eq!(layers, vec![("hills", -1), ("grass", 1)]);
+        
+        let hills = &tilemap.layers()[0];
+        assert_eq!(hills.tileset().len(), 3);
+        assert!(hills.tileset()[0].ends_with("wall.png"));
+        // The flipped tile still finds its image
+        assert_eq!(hills.tiles()[3 * 12 + 2], Some(0));
+        assert_eq!(hills.tiles()[3 * 12 + 3], Some(0));
+        assert_eq!(hills.tiles().iter().filter(|tile| tile.is_some()).count(), 2);
+        
+        let grass = &tilemap.layers()[1];
+        assert_eq!(grass.tiles()[4 * 12 + 8], Some(1));
+    }
+    
+    #[test]
+    fn spawns() {
+        let tilemap = load_tiled(SAMPLE, &tileset()).unwrap();
+        let spawns: Vec<_> = tilemap.spawns().iter().map(|spawn| (spawn.entity, spawn.row, spawn.col)).collect();
+        assert_eq!(spawns, vec![
+            (EntityType::Player, 4, 1),
+            (EntityType::Crawler, 4, 6),
+            (EntityType::Goal, 4, 10),
+        ]);
+        assert_eq!(tilemap.spawns()[1].properties.get("speed").map(|speed| &speed[..]), Some("1.5"));
+    }
+    
+    #[test]
+    fn tmx_matches_json() {
+        let tileset = tileset();
+        let json = load_tiled(SAMPLE, &tileset).unwrap();
+        let tmx = load_tiled(SAMPLE_TMX, &tileset).unwrap();
+        assert_eq!((tmx.width(), tmx.height()), (json.width(), json.height()));
+        assert_eq!(tmx.iter().collect::<Vec<_>>(), json.iter().collect::<Vec<_>>());
+        assert_eq!(tmx.layers(), json.layers());
+        assert_eq!(tmx.spawns(), json.spawns());
+        assert_eq!(tmx.metadata(), json.metadata());
+    }
+    
+    #[test]
+    fn unknown_object() {
+        let json = include_str!("../../assets/levels/tiled_sample.json").replace("\"goal\"", "\"dragon\"");
+        match parse_tiled_json(&mut json.as_bytes(), Path::new("assets/levels"), &tileset()) {
+            Err(Error::UnknownObject(ref kind)) if kind == "dragon" => {},
+            other => panic!("expected an unknown object, got {:?}", other.map(|_| ())),
+        }
+    }
+}
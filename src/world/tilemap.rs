@@ -1,33 +1,161 @@
-use std::io::{self, BufRead};
+use std::cmp;
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, Read};
 use std::str::FromStr;
 use rustc_serialize::{Encoder, Decoder, Encodable, Decodable};
+use rustc_serialize::json;
 use world::item::Item;
 use world::entities::EntityType;
+use world::tileset::{self, Tileset, TileDef};
 
 #[derive(Copy, Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
 pub enum InputTile {
     Open,
-    Wall,
     Spawn(u8),
-    Item(char),
+    // Looked up in the tileset
+    Tile(char),
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, RustcEncodable, RustcDecodable)]
 pub enum Tile {
     Open,
-    Wall,
-    Breakable(Item),
+    // Id of the tile's definition in the tileset
+    Block(u32),
+}
+
+// Purely visual tiles drawn along with the main collision layer
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct TileLayer {
+    name: String,
+    // Layers are drawn in increasing order. Ones above MAIN_LAYER_ORDER are
+    // drawn in front of sprites.
+    order: i32,
+    // Images making up the layer's tileset
+    tileset: Vec<String>,
+    // Index into the tileset for each tile, if anything is drawn there
+    tiles: Vec<Option<u32>>,
 }
 
-#[derive(Clone, Debug)]
+pub const MAIN_LAYER_ORDER: i32 = 0;
+
+impl TileLayer {
+    pub fn new(name: String, order: i32, tileset: Vec<String>, tiles: Vec<Option<u32>>) -> TileLayer {
+        TileLayer {
+            name: name,
+            order: order,
+            tileset: tileset,
+            tiles: tiles,
+        }
+    }
+    
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    
+    pub fn order(&self) -> i32 {
+        self.order
+    }
+    
+    pub fn tileset(&self) -> &[String] {
+        &self.tileset
+    }
+    
+    pub fn tiles(&self) -> &[Option<u32>] {
+        &self.tiles
+    }
+    
+    pub fn in_front(&self) -> bool {
+        self.order > MAIN_LAYER_ORDER
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Spawn {
+    pub entity: EntityType,
+    pub row: u32,
+    pub col: u32,
+    // Extra settings for the entity, from map formats that have them
+    pub properties: BTreeMap<String, String>,
+}
+
+// The main layer is split into square chunks so that changes to it can be
+// tracked, and redrawn, a piece at a time
+pub const CHUNK_SIZE: u32 = 32;
+
+// Chunks on the right and bottom edges of the map are only partly used
+#[derive(Clone, Debug, RustcEncodable, RustcDecodable)]
+struct Chunk {
+    tiles: Vec<Tile>,
+    collision: Vec<bool>,
+    // Goes up whenever anything in the chunk changes
+    revision: u32,
+}
+
+// Revisions only matter to whoever is watching the map, not to what's on it
+impl PartialEq for Chunk {
+    fn eq(&self, other: &Chunk) -> bool {
+        self.tiles == other.tiles && self.collision == other.collision
+    }
+}
+
+// Decoding checks that the parts of the map agree with each other, so saved
+// games and editor output can't leave it in a state the game would trip over
+#[derive(Clone, Debug, PartialEq, RustcEncodable)]
 pub struct Tilemap {
     width: u32,
     height: u32,
-    collision_map: Vec<bool>,
-    tile_map: Vec<Tile>,
-    spawns: Vec<(EntityType, u32, u32)>,
+    chunks: Vec<Chunk>,
+    spawns: Vec<Spawn>,
+    layers: Vec<TileLayer>,
+    metadata: BTreeMap<String, String>,
+    tileset: Tileset,
 }
 
+impl Decodable for Tilemap {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Tilemap, D::Error> {
+        d.read_struct("Tilemap", 7, |d| {
+            let tilemap = Tilemap {
+                width: try!(d.read_struct_field("width", 0, Decodable::decode)),
+                height: try!(d.read_struct_field("height", 1, Decodable::decode)),
+                chunks: try!(d.read_struct_field("chunks", 2, Decodable::decode)),
+                spawns: try!(d.read_struct_field("spawns", 3, Decodable::decode)),
+                layers: try!(d.read_struct_field("layers", 4, Decodable::decode)),
+                metadata: try!(d.read_struct_field("metadata", 5, Decodable::decode)),
+                tileset: try!(d.read_struct_field("tileset", 6, Decodable::decode)),
+            };
+            match tilemap.check() {
+                Ok(()) => Ok(tilemap),
+                Err(e) => Err(d.error(&e.to_string())),
+            }
+        })
+    }
+}
+
+// Text maps start with a header like
+//
+//     ecsmap 1
+//     width 80
+//     height 15
+//     name Level 1
+//     ---
+//
+// Any keys besides width and height are kept as metadata. Maps from before
+// the header was versioned instead start with a single "height width" line.
+//
+// The rows of the main layer can be followed by decoration layers:
+//
+//     layer clouds
+//     order -1
+//     tileset assets/tilesets/basic/wall.png assets/tilesets/basic/breakable.png
+//     ---
+//
+// and then a full set of rows where '_' is empty and 0-9, a-z pick a tile
+// from the layer's tileset.
+pub const TEXT_MAGIC: &'static str = "ecsmap";
+pub const TEXT_VERSION: u32 = 1;
+
 impl Tilemap {
     pub fn width(&self) -> u32 {
         self.width
@@ -37,182 +165,917 @@ impl Tilemap {
         self.height
     }
     
+    pub fn tileset(&self) -> &Tileset {
+        &self.tileset
+    }
+    
     pub fn filled_at(&self, row: u32, col: u32) -> bool {
-        self.collision_map[(row * self.width + col) as usize]
+        let (chunk, i) = self.index(row, col);
+        self.chunks[chunk].collision[i]
     }
     
-    pub fn tiles(&self) -> &[Tile] {
-        &self.tile_map
+    pub fn set_filled(&mut self, row: u32, col: u32, filled: bool) {
+        let (chunk, i) = self.index(row, col);
+        let chunk = &mut self.chunks[chunk];
+        chunk.collision[i] = filled;
+        chunk.revision += 1;
     }
     
     pub fn tile_at(&self, row: u32, col: u32) -> &Tile {
-        &self.tile_map[(row * self.width + col) as usize]
+        let (chunk, i) = self.index(row, col);
+        &self.chunks[chunk].tiles[i]
     }
     
+    // Counts as a change to the tile's chunk whether or not anything is written
     pub fn tile_at_mut(&mut self, row: u32, col: u32) -> &mut Tile {
-        &mut self.tile_map[(row * self.width + col) as usize]
+        let (chunk, i) = self.index(row, col);
+        let chunk = &mut self.chunks[chunk];
+        chunk.revision += 1;
+        &mut chunk.tiles[i]
+    }
+    
+    pub fn chunks_wide(&self) -> u32 {
+        (self.width + CHUNK_SIZE - 1) / CHUNK_SIZE
+    }
+    
+    pub fn chunks_high(&self) -> u32 {
+        (self.height + CHUNK_SIZE - 1) / CHUNK_SIZE
+    }
+    
+    // Chunks are numbered row by row from the top left
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+    
+    pub fn chunk_revision(&self, chunk: usize) -> u32 {
+        self.chunks[chunk].revision
+    }
+    
+    pub fn chunk_at(&self, row: u32, col: u32) -> usize {
+        self.index(row, col).0
+    }
+    
+    // The (row, col) of a chunk's top left tile, and how many rows and
+    // columns of it are on the map
+    pub fn chunk_rect(&self, chunk: usize) -> (u32, u32, u32, u32) {
+        let row = chunk as u32 / self.chunks_wide() * CHUNK_SIZE;
+        let col = chunk as u32 % self.chunks_wide() * CHUNK_SIZE;
+        let rows = cmp::min(CHUNK_SIZE, self.height - row);
+        let cols = cmp::min(CHUNK_SIZE, self.width - col);
+        (row, col, rows, cols)
+    }
+    
+    // The chunk a tile is in, and where it is within the chunk
+    fn index(&self, row: u32, col: u32) -> (usize, usize) {
+        debug_assert!(row < self.height && col < self.width);
+        let chunk = row / CHUNK_SIZE * self.chunks_wide() + col / CHUNK_SIZE;
+        let i = row % CHUNK_SIZE * CHUNK_SIZE + col % CHUNK_SIZE;
+        (chunk as usize, i as usize)
+    }
+    
+    // The definition of the tile at a position, if it isn't open
+    pub fn def_at(&self, row: u32, col: u32) -> Option<&TileDef> {
+        match *self.tile_at(row, col) {
+            Tile::Open => None,
+            Tile::Block(id) => Some(self.tileset.def(id)),
+        }
+    }
+    
+    pub fn one_way_at(&self, row: u32, col: u32) -> bool {
+        self.def_at(row, col).map(|def| def.one_way).unwrap_or(false)
+    }
+    
+    pub fn damage_at(&self, row: u32, col: u32) -> u32 {
+        self.def_at(row, col).map(|def| def.damage).unwrap_or(0)
+    }
+    
+    // Replaces a tile and keeps the collision map in sync with it
+    pub fn set_tile(&mut self, row: u32, col: u32, tile: Tile) {
+        *self.tile_at_mut(row, col) = tile;
+        let solid = self.def_at(row, col).map(|def| def.solid).unwrap_or(false);
+        self.set_filled(row, col, solid);
     }
     
-    pub fn spawns(&self) -> &[(EntityType, u32, u32)] {
+    // Opens up a breakable block, returning the item that was inside it
+    pub fn break_tile(&mut self, row: u32, col: u32) -> Option<Item> {
+        let item = match self.def_at(row, col) {
+            Some(def) if def.breakable => def.item,
+            _ => return None,
+        };
+        self.set_tile(row, col, Tile::Open);
+        Some(item)
+    }
+    
+    pub fn spawns(&self) -> &[Spawn] {
         &self.spawns
     }
     
-    pub fn parse_text_map<R: BufRead>(reader: R) -> Res<Tilemap> {
-        let (width, height, input_tiles) = try!(Tilemap::parse_text_map_input(reader));
-        Tilemap::parse_input(width, height, &input_tiles)
+    pub fn add_spawn(&mut self, spawn: Spawn) {
+        self.spawns.push(spawn);
+    }
+    
+    pub fn layers(&self) -> &[TileLayer] {
+        &self.layers
+    }
+    
+    pub fn add_layer(&mut self, layer: TileLayer) -> Res<()> {
+        let size = try!(tile_count(self.width, self.height).ok_or(Error::BadMapSize {
+            width: self.width,
+            height: self.height,
+            found: layer.tiles.len() as u32,
+        }));
+        try!(check_layer_size(&layer, size));
+        self.layers.push(layer);
+        Ok(())
+    }
+    
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+    
+    pub fn metadata_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.metadata
+    }
+    
+    // Makes sure the tiles, collision map, spawns and layers all fit the map
+    fn check(&self) -> Res<()> {
+        let chunk_size = CHUNK_SIZE * CHUNK_SIZE;
+        let size = try!(tile_count(self.width, self.height).ok_or(Error::BadMapSize {
+            width: self.width,
+            height: self.height,
+            found: (self.chunks.len() as u32).saturating_mul(chunk_size),
+        }));
+        // Can't overflow once the size fits
+        let chunks = self.chunks_wide() * self.chunks_high();
+        if self.chunks.len() as u32 != chunks {
+            return Err(Error::BadChunkCount { expected: chunks, found: self.chunks.len() as u32 });
+        }
+        for chunk in &self.chunks {
+            if chunk.tiles.len() as u32 != chunk_size || chunk.collision.len() as u32 != chunk_size {
+                return Err(Error::BadChunkSize);
+            }
+            for &tile in &chunk.tiles {
+                if let Tile::Block(id) = tile {
+                    if id as usize >= self.tileset.defs().len() {
+                        return Err(Error::BadTileId(id));
+                    }
+                }
+            }
+        }
+        for spawn in &self.spawns {
+            if spawn.row >= self.height || spawn.col >= self.width {
+                return Err(Error::BadSpawn { row: spawn.row, col: spawn.col });
+            }
+        }
+        for layer in &self.layers {
+            try!(check_layer_size(layer, size));
+        }
+        Ok(())
+    }
+    
+    pub fn parse_text_map<R: BufRead>(reader: R, tileset: &Tileset) -> Res<Tilemap> {
+        let map = try!(Tilemap::parse_text_map_input(reader));
+        let mut tilemap = try!(Tilemap::parse_input(
+            map.width, map.height, &map.tiles, tileset
+        ).map_err(|e| e.relocate(&map.row_lines)));
+        tilemap.layers = map.layers;
+        tilemap.metadata = map.metadata;
+        Ok(tilemap)
     }
     
     pub fn parse_encode<R: BufRead, E: Encoder>(
         reader: R, encoder: &mut E
     ) -> Res<Result<(), E::Error>> {
-        let result = try!(Tilemap::parse_text_map_input(reader));
-        Ok(result.encode(encoder))
+        let map = try!(Tilemap::parse_text_map_input(reader));
+        Ok((map.width, map.height, map.tiles, map.layers, map.metadata).encode(encoder))
     }
     
-    pub fn decode_parse<D: Decoder>(decoder: &mut D) -> Result<Res<Tilemap>, D::Error> {
-        let result = try!(<(u32, u32, Vec<InputTile>) as Decodable>::decode(decoder));
-        let (width, height, input_tiles) = result;
-        Ok(Tilemap::parse_input(width, height, &input_tiles))
+    pub fn decode_parse<D: Decoder>(
+        decoder: &mut D, tileset: &Tileset
+    ) -> Result<Res<Tilemap>, D::Error> {
+        let result = try!(<(
+            u32, u32, Vec<InputTile>, Vec<TileLayer>, BTreeMap<String, String>
+        ) as Decodable>::decode(decoder));
+        let (width, height, input_tiles, layers, metadata) = result;
+        Ok(Tilemap::parse_input(width, height, &input_tiles, tileset).and_then(|mut tilemap| {
+            // parse_input already made sure the size fits
+            for layer in &layers {
+                try!(check_layer_size(layer, width * height));
+            }
+            tilemap.layers = layers;
+            tilemap.metadata = metadata;
+            Ok(tilemap)
+        }))
     }
     
-    fn parse_input(width: u32, height: u32, input_tiles: &[InputTile]) -> Res<Tilemap> {
-        let collision_map = input_tiles.iter().map(|&tile| {
-            match tile {
-                InputTile::Open => false,
-                InputTile::Wall => true,
-                InputTile::Spawn(_) => false,
-                InputTile::Item(_) => true,
-            }
-        }).collect();
+    // Errors from here locate tiles by 1-based row and column
+    pub fn parse_input(
+        width: u32, height: u32, input_tiles: &[InputTile], tileset: &Tileset
+    ) -> Res<Tilemap> {
+        let size_error = Error::BadMapSize {
+            width: width,
+            height: height,
+            found: input_tiles.len() as u32,
+        };
+        match tile_count(width, height) {
+            Some(size) if size == input_tiles.len() as u32 => {},
+            _ => return Err(size_error),
+        }
         
-        let tile_map: Result<_, Error> = input_tiles.iter().map(|&tile| {
+        let location = |i: usize| Location {
+            line: i as u32 / width + 1,
+            column: i as u32 % width + 1,
+        };
+        
+        let tile_map: Result<Vec<_>, Error> = input_tiles.iter().enumerate().map(|(i, &tile)| {
             Ok(match tile {
                 InputTile::Open => Tile::Open,
-                InputTile::Wall => Tile::Wall,
                 InputTile::Spawn(_) => Tile::Open,
-                InputTile::Item(id) => Tile::Breakable(try!(Item::parse(id)))
+                InputTile::Tile(c) if tileset.is_open(c) => Tile::Open,
+                InputTile::Tile(c) => Tile::Block(try!(tileset.lookup(c).ok_or(
+                    Error::UnknownTile { at: location(i), tile: c }
+                ))),
             })
         }).collect();
+        let tile_map = try!(tile_map);
+        
+        let collision_map: Vec<_> = tile_map.iter().map(|&tile| {
+            match tile {
+                Tile::Open => false,
+                Tile::Block(id) => tileset.def(id).solid,
+            }
+        }).collect();
         
         let spawns: Result<_, Error> = input_tiles.iter().enumerate().filter_map(|(i, &tile)| {
-            let x = i as u32 % width;
-            let y = i as u32 / width;
             match tile {
-                InputTile::Spawn(id) => Some((id, x, y)),
+                InputTile::Spawn(id) => Some((i, id)),
                 _ => None,
             }
-        }).map(|(id, x, y)| {
-            let entity = try!(EntityType::parse(id));
-            Ok((entity, x, y))
+        }).map(|(i, id)| {
+            let entity = try!(EntityType::parse(id).map_err(|id| {
+                Error::InvalidEntity { at: location(i), id: id }
+            }));
+            Ok(Spawn {
+                entity: entity,
+                row: i as u32 / width,
+                col: i as u32 % width,
+                properties: BTreeMap::new(),
+            })
         }).collect();
         
         Ok(Tilemap {
             width: width,
             height: height,
-            collision_map: collision_map,
-            tile_map: try!(tile_map),
+            chunks: chunked(width, height, &tile_map, &collision_map),
             spawns: try!(spawns),
+            layers: Vec::new(),
+            metadata: BTreeMap::new(),
+            tileset: tileset.clone(),
         })
     }
     
-    fn parse_text_map_input<R: BufRead>(mut reader: R) -> Res<(u32, u32, Vec<InputTile>)> {
+    fn parse_text_map_input<R: BufRead>(mut reader: R) -> Res<TextMap> {
+        let header = try!(parse_header(&mut reader));
+        let width = header.width;
+        let height = header.height;
+        let size = try!(tile_count(width, height).ok_or(Error::BadMapSize {
+            width: width,
+            height: height,
+            found: 0,
+        }));
+        
+        // Parse map lines
+        let (tiles, row_lines, mut line_num) = try!(parse_rows(
+            &mut reader, header.lines + 1, width, size, |c, _| {
+                Ok(match c {
+                    // Entity spawns
+                    c if c.is_digit(10) => InputTile::Spawn(c.to_digit(10).unwrap() as u8),
+                    // Anything else is up to the tileset
+                    c => InputTile::Tile(c),
+                })
+            }
+        ));
+        
+        // Then any decoration layers
+        let mut layers = Vec::new();
         let mut line = String::new();
+        loop {
+            line.clear();
+            if try!(reader.read_line(&mut line)) == 0 {
+                break;
+            }
+            let layer_line = line_num;
+            line_num += 1;
+            
+            let name = {
+                let mut split = line.split_whitespace();
+                match (split.next(), split.next()) {
+                    (None, _) => continue,
+                    (Some("layer"), Some(name)) => name.to_string(),
+                    _ => return Err(Error::BadLayerHeader(Location { line: layer_line, column: 0 })),
+                }
+            };
+            
+            let mut order = MAIN_LAYER_ORDER - 1;
+            let mut tileset = Vec::new();
+            for (entry_line, key, value) in try!(parse_entries(&mut reader, &mut line_num)) {
+                let bad_entry = Error::BadLayerHeader(Location { line: entry_line, column: 0 });
+                match &key[..] {
+                    "order" => order = try!(i32::from_str(&value).map_err(|_| bad_entry)),
+                    "tileset" => tileset = value.split_whitespace().map(|p| p.to_string()).collect(),
+                    _ => return Err(bad_entry),
+                }
+            }
+            
+            let (layer_tiles, _, next_line) = try!(parse_rows(
+                &mut reader, line_num, width, size, |c, at| {
+                    match c {
+                        '_' => Ok(None),
+                        c => c.to_digit(36).map(Some).ok_or(Error::InvalidLayerTile {
+                            at: at,
+                            tile: c,
+                        }),
+                    }
+                }
+            ));
+            line_num = next_line;
+            
+            let layer = TileLayer::new(name, order, tileset, layer_tiles);
+            try!(check_layer_size(&layer, size));
+            layers.push(layer);
+        }
         
-        // Parse width, height
-        try!(reader.read_line(&mut line));
-        let width;
-        let height;
-        {
-            let mut split = line.split_whitespace();
-            let height_s = try!(split.next().ok_or(Error::BadMapData));
-            let width_s = try!(split.next().ok_or(Error::BadMapData));
-            width = try!(u32::from_str(width_s).map_err(|_| Error::BadMapData));
-            height = try!(u32::from_str(height_s).map_err(|_| Error::BadMapData));
+        Ok(TextMap {
+            width: width,
+            height: height,
+            tiles: tiles,
+            row_lines: row_lines,
+            layers: layers,
+            metadata: header.metadata,
+        })
+    }
+}
+
+struct TextMap {
+    width: u32,
+    height: u32,
+    tiles: Vec<InputTile>,
+    // Line of the file each row of tiles came from
+    row_lines: Vec<u32>,
+    layers: Vec<TileLayer>,
+    metadata: BTreeMap<String, String>,
+}
+
+struct TextHeader {
+    width: u32,
+    height: u32,
+    metadata: BTreeMap<String, String>,
+    // Number of lines the header took up
+    lines: u32,
+}
+
+fn parse_header<R: BufRead>(reader: &mut R) -> Res<TextHeader> {
+    let bad_header = |line| Error::BadHeader(Location { line: line, column: 0 });
+    
+    let mut line = String::new();
+    try!(reader.read_line(&mut line));
+    
+    let (first, second) = {
+        let mut split = line.split_whitespace();
+        (split.next().unwrap_or(""), split.next().unwrap_or(""))
+    };
+    
+    if first != TEXT_MAGIC {
+        // Legacy maps put the height first
+        let height = try!(u32::from_str(first).map_err(|_| bad_header(1)));
+        let width = try!(u32::from_str(second).map_err(|_| bad_header(1)));
+        return Ok(TextHeader {
+            width: width,
+            height: height,
+            metadata: BTreeMap::new(),
+            lines: 1,
+        });
+    }
+    
+    let version = try!(u32::from_str(second).map_err(|_| bad_header(1)));
+    if version != TEXT_VERSION {
+        return Err(Error::UnsupportedVersion {
+            at: Location { line: 1, column: 0 },
+            version: version,
+        });
+    }
+    
+    let mut width = None;
+    let mut height = None;
+    let mut metadata = BTreeMap::new();
+    let mut line_num = 2;
+    for (entry_line, key, value) in try!(parse_entries(reader, &mut line_num)) {
+        match &key[..] {
+            "width" => {
+                width = Some(try!(u32::from_str(&value).map_err(|_| bad_header(entry_line))));
+            },
+            "height" => {
+                height = Some(try!(u32::from_str(&value).map_err(|_| bad_header(entry_line))));
+            },
+            _ => {
+                metadata.insert(key.clone(), value);
+            }
+        }
+    }
+    
+    Ok(TextHeader {
+        width: try!(width.ok_or(Error::MissingDimension("width"))),
+        height: try!(height.ok_or(Error::MissingDimension("height"))),
+        metadata: metadata,
+        lines: line_num - 1,
+    })
+}
+
+// Reads "key value" lines up to a "---" line, starting at `line_num`.
+// Leaves `line_num` at the line after the "---".
+fn parse_entries<R: BufRead>(reader: &mut R, line_num: &mut u32) -> Res<Vec<(u32, String, String)>> {
+    let mut entries = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let entry_line = *line_num;
+        *line_num += 1;
+        if try!(reader.read_line(&mut line)) == 0 {
+            return Err(Error::BadHeader(Location { line: entry_line, column: 0 }));
         }
         
-        // Parse map lines
-        let mut tiles = Vec::new();
-        for c in reader.chars() {
-            let c = try!(c);
-            match c {
-                '\n' | '\r' => {
-                    let len = tiles.len() as u32;
-                    if len % width != 0 || len > width * height {
-                        return Err(Error::BadMapSize);
-                    }
-                    if len == width * height {
+        let entry = line.trim();
+        if entry == "---" {
+            return Ok(entries);
+        }
+        if entry.is_empty() {
+            continue;
+        }
+        
+        let mut split = entry.splitn(2, char::is_whitespace);
+        let key = split.next().unwrap();
+        let value = split.next().unwrap_or("").trim();
+        entries.push((entry_line, key.to_string(), value.to_string()));
+    }
+}
+
+// Reads rows of tiles starting at `line_num` until there are `size` of them or
+// the input runs out. Returns the tiles, the line each row came
+// from and the next unread line.
+fn parse_rows<R, T, F>(
+    reader: &mut R, mut line_num: u32, width: u32, size: u32, mut parse_tile: F
+) -> Res<(Vec<T>, Vec<u32>, u32)>
+    where R: BufRead, F: FnMut(char, Location) -> Res<T> {
+    
+    let mut tiles = Vec::new();
+    let mut row_lines = Vec::new();
+    let mut column = 0;
+    for c in reader.by_ref().chars() {
+        let c = try!(c.map_err(|e| match e {
+            io::CharsError::NotUtf8 => Error::NotUtf8(Location {
+                line: line_num,
+                column: column + 1,
+            }),
+            io::CharsError::Other(e) => Error::Io(e),
+        }));
+        match c {
+            '\n' | '\r' => {
+                if column != 0 {
+                    try!(end_row(line_num, column, width));
+                    row_lines.push(line_num);
+                    column = 0;
+                }
+                if c == '\n' {
+                    line_num += 1;
+                    if tiles.len() as u32 == size {
                         break;
                     }
-                },
-                // Open air
-                '_' => {
-                    tiles.push(InputTile::Open);
-                },
-                // Walls
-                '#' => {
-                    tiles.push(InputTile::Wall);
-                },
-                // Entity spawns
-                c if c.is_digit(10) => {
-                    let id = c.to_digit(10).unwrap() as u8;
-                    tiles.push(InputTile::Spawn(id));
-                },
-                // Anything else must be an item block
-                c => {
-                    tiles.push(InputTile::Item(c));
                 }
+                continue;
+            },
+            c => {
+                let at = Location { line: line_num, column: column + 1 };
+                tiles.push(try!(parse_tile(c, at)));
             }
         }
-        
-        Ok((width, height, tiles))
+        column += 1;
+    }
+    
+    // The last row may not end in a newline
+    if column != 0 {
+        try!(end_row(line_num, column, width));
+        row_lines.push(line_num);
+        line_num += 1;
+    }
+    
+    Ok((tiles, row_lines, line_num))
+}
+
+// Splits row by row tiles into chunks
+fn chunked(width: u32, height: u32, tiles: &[Tile], collision: &[bool]) -> Vec<Chunk> {
+    let chunks_wide = (width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+    let chunks_high = (height + CHUNK_SIZE - 1) / CHUNK_SIZE;
+    let chunk_size = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+    
+    let mut chunks: Vec<_> = (0..chunks_wide * chunks_high).map(|_| Chunk {
+        tiles: vec![Tile::Open; chunk_size],
+        collision: vec![false; chunk_size],
+        revision: 0,
+    }).collect();
+    for (i, (&tile, &filled)) in tiles.iter().zip(collision).enumerate() {
+        let row = i as u32 / width;
+        let col = i as u32 % width;
+        let chunk = &mut chunks[(row / CHUNK_SIZE * chunks_wide + col / CHUNK_SIZE) as usize];
+        let j = (row % CHUNK_SIZE * CHUNK_SIZE + col % CHUNK_SIZE) as usize;
+        chunk.tiles[j] = tile;
+        chunk.collision[j] = filled;
+    }
+    chunks
+}
+
+// Number of tiles on a map, unless it's too big to count or to split into
+// chunks
+pub fn tile_count(width: u32, height: u32) -> Option<u32> {
+    width.checked_add(CHUNK_SIZE - 1)
+        .and(height.checked_add(CHUNK_SIZE - 1))
+        .and(width.checked_mul(height))
+}
+
+fn check_layer_size(layer: &TileLayer, size: u32) -> Res<()> {
+    if layer.tiles.len() as u32 != size {
+        return Err(Error::BadLayerSize {
+            layer: layer.name.clone(),
+            expected: size,
+            found: layer.tiles.len() as u32,
+        });
     }
+    Ok(())
+}
+
+fn end_row(line: u32, length: u32, width: u32) -> Res<()> {
+    if length != width {
+        return Err(Error::BadRowLength {
+            at: Location { line: line, column: 0 },
+            expected: width,
+            found: length,
+        });
+    }
+    Ok(())
 }
 
 pub type Res<T> = Result<T, Error>;
 
+// 1-based position in a map file. A column of 0 refers to the whole line.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.column > 0 {
+            write!(f, "{}:{}", self.line, self.column)
+        } else {
+            write!(f, "{}", self.line)
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
-    BadMapData,
-    BadMapSize,
-    InvalidItem(char),
-    InvalidEntity(u8),
-    NotUtf8,
+    BadHeader(Location),
+    UnsupportedVersion { at: Location, version: u32 },
+    MissingDimension(&'static str),
+    BadRowLength { at: Location, expected: u32, found: u32 },
+    BadMapSize { width: u32, height: u32, found: u32 },
+    UnknownTile { at: Location, tile: char },
+    InvalidEntity { at: Location, id: u8 },
+    BadLayerHeader(Location),
+    BadLayerSize { layer: String, expected: u32, found: u32 },
+    InvalidLayerTile { at: Location, tile: char },
+    // Importing from Tiled
+    Json(json::ParserError),
+    BadXml { at: Location, reason: String },
+    BadTiled(String),
+    UnsupportedTiled(String),
+    UnknownGid(u32),
+    UnknownTileName(String),
+    UnknownObject(String),
+    Tileset(tileset::Error),
+    // Decoding
+    BadTileId(u32),
+    BadChunkCount { expected: u32, found: u32 },
+    BadChunkSize,
+    BadSpawn { row: u32, col: u32 },
+    // Binary levels
+    BadBinary(String),
+    BadChecksum { expected: u32, found: u32 },
+    UnsupportedBinaryVersion(u32),
+    NotUtf8(Location),
     Io(io::Error),
+    InFile { path: String, error: Box<Error> },
 }
 
-impl From<io::Error> for Error {
-    fn from(io: io::Error) -> Error {
-        Error::Io(io)
+impl Error {
+    pub fn in_file(path: &str, error: Error) -> Error {
+        Error::InFile {
+            path: path.into(),
+            error: Box::new(error),
+        }
+    }
+    
+    pub fn location(&self) -> Option<Location> {
+        match *self {
+            Error::BadHeader(at) |
+            Error::UnsupportedVersion { at, .. } |
+            Error::BadRowLength { at, .. } |
+            Error::UnknownTile { at, .. } |
+            Error::InvalidEntity { at, .. } |
+            Error::BadLayerHeader(at) |
+            Error::InvalidLayerTile { at, .. } |
+            Error::BadXml { at, .. } |
+            Error::NotUtf8(at) => Some(at),
+            Error::Tileset(ref e) => e.line().map(|line| Location { line: line, column: 0 }),
+            Error::InFile { ref error, .. } => error.location(),
+            _ => None,
+        }
+    }
+    
+    // Swaps the tile rows parse_input reports for the lines they came from
+    fn relocate(self, row_lines: &[u32]) -> Error {
+        let fix = |at: Location| Location {
+            line: row_lines.get(at.line as usize - 1).cloned().unwrap_or(at.line),
+            column: at.column,
+        };
+        match self {
+            Error::UnknownTile { at, tile } => Error::UnknownTile { at: fix(at), tile: tile },
+            Error::InvalidEntity { at, id } => Error::InvalidEntity { at: fix(at), id: id },
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::BadHeader(at) => write!(f, "{} bad map header", at),
+            Error::UnsupportedVersion { at, version } => {
+                write!(f, "{} unsupported map version {}", at, version)
+            },
+            Error::MissingDimension(key) => write!(f, "map header has no {}", key),
+            Error::BadRowLength { at, expected, found } => {
+                write!(f, "{} row is {} tiles wide, expected {}", at, found, expected)
+            },
+            Error::BadMapSize { width, height, found } => {
+                write!(f, "map is {} by {} tiles but has {} of them", width, height, found)
+            },
+            Error::UnknownTile { at, tile } => write!(f, "{} unknown tile '{}'", at, tile),
+            Error::InvalidEntity { at, id } => write!(f, "{} unknown entity {}", at, id),
+            Error::BadLayerHeader(at) => write!(f, "{} bad layer header", at),
+            Error::BadLayerSize { ref layer, expected, found } => {
+                write!(f, "layer {} has {} tiles, expected {}", layer, found, expected)
+            },
+            Error::InvalidLayerTile { at, tile } => write!(f, "{} unknown layer tile '{}'", at, tile),
+            Error::Json(ref e) => write!(f, "{}", e),
+            Error::BadXml { at, ref reason } => write!(f, "{} bad XML: {}", at, reason),
+            Error::BadTiled(ref reason) => write!(f, "bad Tiled map: {}", reason),
+            Error::UnsupportedTiled(ref feature) => write!(f, "{} aren't supported", feature),
+            Error::UnknownGid(gid) => write!(f, "tile {} doesn't stand for any tile definition", gid),
+            Error::UnknownTileName(ref name) => write!(f, "no tile definition named {}", name),
+            Error::UnknownObject(ref kind) => write!(f, "unknown object type {}", kind),
+            Error::Tileset(ref e) => write!(f, "{}", e),
+            Error::BadTileId(id) => write!(f, "tileset has no tile {}", id),
+            Error::BadChunkCount { expected, found } => {
+                write!(f, "map has {} chunks, expected {}", found, expected)
+            },
+            Error::BadChunkSize => write!(f, "chunk has the wrong number of tiles"),
+            Error::BadSpawn { row, col } => write!(f, "spawn at {}, {} is outside the map", row, col),
+            Error::BadBinary(ref reason) => write!(f, "bad binary level: {}", reason),
+            Error::BadChecksum { expected, found } => {
+                write!(f, "checksum is {:08x}, expected {:08x}", found, expected)
+            },
+            Error::UnsupportedBinaryVersion(version) => {
+                write!(f, "unsupported binary level version {}", version)
+            },
+            Error::NotUtf8(at) => write!(f, "{} invalid UTF-8", at),
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::InFile { ref path, ref error } => {
+                if error.location().is_some() {
+                    write!(f, "{}:{}", path, error)
+                } else {
+                    write!(f, "{}: {}", path, error)
+                }
+            },
+        }
     }
 }
 
-impl From<io::CharsError> for Error {
-    fn from(e: io::CharsError) -> Error {
-        match e {
-            io::CharsError::NotUtf8 => Error::NotUtf8,
-            io::CharsError::Other(e) => From::from(e),
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::BadHeader(_) => "bad map header",
+            Error::UnsupportedVersion { .. } => "unsupported map version",
+            Error::MissingDimension(_) => "map header is missing a dimension",
+            Error::BadRowLength { .. } => "map row has the wrong width",
+            Error::BadMapSize { .. } => "map has the wrong number of tiles",
+            Error::UnknownTile { .. } => "unknown tile",
+            Error::InvalidEntity { .. } => "unknown entity",
+            Error::BadLayerHeader(_) => "bad layer header",
+            Error::BadLayerSize { .. } => "layer has the wrong number of tiles",
+            Error::InvalidLayerTile { .. } => "unknown layer tile",
+            Error::Json(ref e) => error::Error::description(e),
+            Error::BadXml { .. } => "bad XML",
+            Error::BadTiled(_) => "bad Tiled map",
+            Error::UnsupportedTiled(_) => "unsupported Tiled feature",
+            Error::UnknownGid(_) => "Tiled tile has no tile definition",
+            Error::UnknownTileName(_) => "unknown tile definition",
+            Error::UnknownObject(_) => "unknown object type",
+            Error::Tileset(ref e) => error::Error::description(e),
+            Error::BadTileId(_) => "unknown tile id",
+            Error::BadChunkCount { .. } => "map has the wrong number of chunks",
+            Error::BadChunkSize => "chunk has the wrong number of tiles",
+            Error::BadSpawn { .. } => "spawn is outside the map",
+            Error::BadBinary(_) => "bad binary level",
+            Error::BadChecksum { .. } => "binary level checksum doesn't match",
+            Error::UnsupportedBinaryVersion(_) => "unsupported binary level version",
+            Error::NotUtf8(_) => "map is not valid UTF-8",
+            Error::Io(ref e) => error::Error::description(e),
+            Error::InFile { error: ref inner, .. } => error::Error::description(&**inner),
+        }
+    }
+    
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            Error::Json(ref e) => Some(e),
+            Error::Tileset(ref e) => Some(e),
+            Error::InFile { ref error, .. } => Some(&**error),
+            _ => None,
         }
     }
 }
 
-impl From<char> for Error {
-    fn from(c: char) -> Error {
-        Error::InvalidItem(c)
+impl From<io::Error> for Error {
+    fn from(io: io::Error) -> Error {
+        Error::Io(io)
     }
 }
 
-impl From<u8> for Error {
-    fn from(id: u8) -> Error {
-        Error::InvalidEntity(id)
+impl From<tileset::Error> for Error {
+    fn from(e: tileset::Error) -> Error {
+        Error::Tileset(e)
     }
 }
 
-pub fn load_map(path: &str) -> Tilemap {
+pub fn load_map(path: &str, tileset: &Tileset) -> Res<Tilemap> {
     use std::fs::File;
     use std::io::BufReader;
-    let file = File::open(path).unwrap();
+    let file = try!(File::open(path).map_err(|e| Error::in_file(path, Error::Io(e))));
     let buf = BufReader::new(file);
-    Tilemap::parse_text_map(buf).unwrap()
+    Tilemap::parse_text_map(buf, tileset).map_err(|e| Error::in_file(path, e))
 }
 
-
+#[cfg(test)]
+mod tests {
+    use std::{iter, u32};
+    use rustc_serialize::json;
+    use super::{Error, Location, Tilemap, MAIN_LAYER_ORDER};
+    use world::tileset;
+    use test_util::{parse_map, tileset};
+    
+    fn open(tiles: usize) -> String {
+        iter::repeat('_').take(tiles).collect()
+    }
+    
+    fn error(map: &str) -> Error {
+        Error::in_file("level3.txt", Tilemap::parse_text_map(map.as_bytes(), &tileset()).unwrap_err())
+    }
+    
+    #[test]
+    fn error_locations() {
+        let mut map = String::from("11 40\n");
+        for _ in 0..10 {
+            map.push_str(&format!("{}\n", open(40)));
+        }
+        map.push_str(&format!("{}Z\n", open(39)));
+        let e = error(&map);
+        assert_eq!(e.location(), Some(Location { line: 12, column: 40 }));
+        assert_eq!(e.to_string(), "level3.txt:12:40 unknown tile 'Z'");
+        
+        assert_eq!(error("2 3\n_9_\n___\n").to_string(), "level3.txt:2:2 unknown entity 9");
+        assert_eq!(error("2 3\r\n___\r\n_Z_\r\n").to_string(), "level3.txt:3:2 unknown tile 'Z'");
+    }
+    
+    #[test]
+    fn blank_lines_between_rows() {
+        assert_eq!(error("2 3\n\n___\n\n\n__Z\n").to_string(), "level3.txt:6:3 unknown tile 'Z'");
+        assert_eq!(error("2 3\n___\n\n__\n").to_string(), "level3.txt:4 row is 2 tiles wide, expected 3");
+    }
+    
+    #[test]
+    fn header_and_size_errors() {
+        assert_eq!(error("x 3\n___\n").to_string(), "level3.txt:1 bad map header");
+        assert_eq!(error("3 3\n___\n").to_string(), "level3.txt: map is 3 by 3 tiles but has 3 of them");
+        
+        let bad_tileset = Error::in_file("tiles.cfg", From::from(tileset::Error::BadLine(3)));
+        assert_eq!(bad_tileset.to_string(), "tiles.cfg:3 bad tileset line");
+    }
+    
+    #[test]
+    fn versioned_header() {
+        let map = parse_map("ecsmap 1\nwidth 3\nheight 2\nname Level 1\n\n---\n___\n_#_\n");
+        assert_eq!((map.width(), map.height()), (3, 2));
+        assert_eq!(map.metadata().get("name").map(|name| &name[..]), Some("Level 1"));
+        assert_eq!(map.metadata().len(), 1);
+        
+        // Rows are located after the header's lines
+        assert_eq!(error("ecsmap 1\nheight 2\nwidth 3\n---\n___\n_Z_\n").to_string(),
+                   "level3.txt:6:2 unknown tile 'Z'");
+        
+        assert_eq!(error("ecsmap 2\nwidth 3\nheight 2\n---\n").to_string(),
+                   "level3.txt:1 unsupported map version 2");
+        assert_eq!(error("ecsmap 1\nwidth 3\n---\n").to_string(),
+                   "level3.txt: map header has no height");
+        assert_eq!(error("ecsmap 1\nwidth three\n").to_string(), "level3.txt:2 bad map header");
+        assert_eq!(error("ecsmap 1\nwidth 3\nheight 2\n").to_string(), "level3.txt:4 bad map header");
+    }
+    
+    #[test]
+    fn legacy_header() {
+        let map = parse_map("2 3\n___\n_#_\n");
+        assert_eq!((map.width(), map.height()), (3, 2));
+        assert!(map.metadata().is_empty());
+        assert!(map.filled_at(1, 1) && !map.filled_at(0, 1));
+    }
+    
+    #[test]
+    fn layer_blocks() {
+        let map = parse_map("2 3\n___\n###\n\n\
+                             layer clouds\norder 2\ntileset a.png b.png\n---\n_1_\n0__\n\
+                             layer back\n---\n___\n__z\n");
+        let layers = map.layers();
+        assert_eq!(layers.len(), 2);
+        
+        assert_eq!((layers[0].name(), layers[0].order()), ("clouds", 2));
+        assert_eq!(layers[0].tileset(), &["a.png".to_string(), "b.png".to_string()][..]);
+        assert_eq!(layers[0].tiles(), &[None, Some(1), None, Some(0), None, None][..]);
+        assert!(layers[0].in_front());
+        
+        // Layers without an order go just behind the main layer
+        assert_eq!((layers[1].name(), layers[1].order()), ("back", MAIN_LAYER_ORDER - 1));
+        assert!(layers[1].tileset().is_empty());
+        assert_eq!(layers[1].tiles()[5], Some(35));
+    }
+    
+    #[test]
+    fn layer_errors() {
+        assert_eq!(error("2 3\n___\n###\nclouds\n").to_string(), "level3.txt:4 bad layer header");
+        assert_eq!(error("2 3\n___\n###\nlayer clouds\nspeed 2\n---\n").to_string(),
+                   "level3.txt:5 bad layer header");
+        assert_eq!(error("2 3\n___\n###\nlayer clouds\norder 1\n").to_string(),
+                   "level3.txt:6 bad map header");
+        assert_eq!(error("2 3\n___\n###\nlayer clouds\n---\n___\n_!_\n").to_string(),
+                   "level3.txt:7:2 unknown layer tile '!'");
+        assert_eq!(error("2 3\n___\n###\nlayer clouds\n---\n___\n").to_string(),
+                   "level3.txt: layer clouds has 3 tiles, expected 6");
+    }
+    
+    #[test]
+    fn decode_round_trip() {
+        let tilemap = parse_map("3 5\n__#__\n_____\n#####\n");
+        let encoded = json::encode(&tilemap).unwrap();
+        assert_eq!(json::decode::<Tilemap>(&encoded).unwrap(), tilemap);
+    }
+    
+    #[test]
+    fn decode_huge_size() {
+        let encoded = json::encode(&parse_map("3 5\n__#__\n_____\n#####\n")).unwrap();
+        assert!(encoded.contains("\"width\":5,\"height\":3"));
+        let huge = encoded.replace(
+            "\"width\":5,\"height\":3",
+            &format!("\"width\":{},\"height\":{}", u32::MAX, u32::MAX)
+        );
+        assert!(json::decode::<Tilemap>(&huge).is_err());
+        
+        // Small enough to multiply, too big to split into chunks
+        let wide = encoded.replace(
+            "\"width\":5,\"height\":3",
+            &format!("\"width\":{},\"height\":1", u32::MAX - 1)
+        );
+        assert!(json::decode::<Tilemap>(&wide).is_err());
+    }
+    
+    #[test]
+    fn huge_size() {
+        let text = format!("ecsmap 1\nwidth {}\nheight {}\n---\n", u32::MAX, u32::MAX);
+        match Tilemap::parse_text_map(text.as_bytes(), &tileset()) {
+            Err(Error::BadMapSize { .. }) => {},
+            other => panic!("expected a map size error, got {:?}", other),
+        }
+        match Tilemap::parse_input(u32::MAX, 2, &[], &tileset()) {
+            Err(Error::BadMapSize { .. }) => {},
+            other => panic!("expected a map size error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+use world::item::Item;
+
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct TileDef {
+    pub name: String,
+    pub symbol: char,
+    // Layer of the tileset texture to draw, if the tile is visible at all
+    pub texture: Option<u32>,
+    pub solid: bool,
+    // Can be landed on from above but passed through from anywhere else
+    pub one_way: bool,
+    pub breakable: bool,
+    // Damage dealt to the player on contact
+    pub damage: u32,
+    // What breaking the tile gives the player
+    pub item: Item,
+}
+
+impl TileDef {
+    pub fn new(name: &str, symbol: char) -> TileDef {
+        TileDef {
+            name: name.into(),
+            symbol: symbol,
+            texture: None,
+            solid: false,
+            one_way: false,
+            breakable: false,
+            damage: 0,
+            item: Item::Empty,
+        }
+    }
+}
+
+// Which characters of a text map make which tiles, and what those tiles do
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Tileset {
+    textures: Vec<String>,
+    open: Vec<char>,
+    defs: Vec<TileDef>,
+}
+
+impl Tileset {
+    pub fn new() -> Tileset {
+        Tileset {
+            textures: Vec::new(),
+            open: Vec::new(),
+            defs: Vec::new(),
+        }
+    }
+    
+    // Images making up the tileset texture, in layer order
+    pub fn textures(&self) -> &[String] {
+        &self.textures
+    }
+    
+    pub fn defs(&self) -> &[TileDef] {
+        &self.defs
+    }
+    
+    pub fn def(&self, id: u32) -> &TileDef {
+        &self.defs[id as usize]
+    }
+    
+    // The id of the tile a character makes
+    pub fn lookup(&self, symbol: char) -> Option<u32> {
+        self.defs.iter().position(|def| def.symbol == symbol).map(|id| id as u32)
+    }
+    
+    // The id of the tile with the given name
+    pub fn find(&self, name: &str) -> Option<u32> {
+        self.defs.iter().position(|def| def.name == name).map(|id| id as u32)
+    }
+    
+    // Whether a character leaves its tile empty
+    pub fn is_open(&self, symbol: char) -> bool {
+        self.open.contains(&symbol)
+    }
+    
+    pub fn add_texture(&mut self, path: &str) -> u32 {
+        self.textures.push(path.into());
+        self.textures.len() as u32 - 1
+    }
+    
+    pub fn add_open(&mut self, symbol: char) {
+        if !self.open.contains(&symbol) {
+            self.open.push(symbol);
+        }
+    }
+    
+    pub fn add(&mut self, def: TileDef) -> u32 {
+        self.defs.push(def);
+        self.defs.len() as u32 - 1
+    }
+    
+    // Lines look like
+    //
+    //     texture assets/tilesets/basic/wall.png
+    //     open _
+    //     tile # wall texture=0 solid
+    //     tile c coin_block texture=1 solid breakable item=coins:1
+    //
+    // where textures are numbered in the order they're listed. Tiles can also
+    // be one_way or have damage=N. Lines starting with `#` are comments.
+    pub fn parse<R: BufRead>(reader: R) -> Res<Tileset> {
+        let mut tileset = Tileset::new();
+        
+        for (i, line) in reader.lines().enumerate() {
+            let line = try!(line);
+            let line_num = i as u32 + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            
+            let mut split = line.split_whitespace();
+            match split.next().unwrap() {
+                "texture" => {
+                    let path = try!(split.next().ok_or(Error::BadLine(line_num)));
+                    tileset.add_texture(path);
+                },
+                "open" => {
+                    for symbols in split {
+                        for symbol in symbols.chars() {
+                            tileset.add_open(symbol);
+                        }
+                    }
+                },
+                "tile" => {
+                    let symbol = try!(split.next().and_then(single_char).ok_or(Error::BadLine(line_num)));
+                    let name = try!(split.next().ok_or(Error::BadLine(line_num)));
+                    if tileset.lookup(symbol).is_some() || tileset.is_open(symbol) {
+                        return Err(Error::DuplicateSymbol(line_num, symbol));
+                    }
+                    
+                    let mut def = TileDef::new(name, symbol);
+                    for property in split {
+                        try!(parse_property(&mut def, property, line_num));
+                    }
+                    if def.texture.map(|t| t as usize >= tileset.textures.len()).unwrap_or(false) {
+                        return Err(Error::UnknownTexture(line_num, def.texture.unwrap()));
+                    }
+                    tileset.add(def);
+                },
+                _ => return Err(Error::BadLine(line_num)),
+            }
+        }
+        
+        Ok(tileset)
+    }
+    
+    pub fn load(path: &str) -> Res<Tileset> {
+        use std::fs::File;
+        use std::io::BufReader;
+        let file = try!(File::open(path));
+        Tileset::parse(BufReader::new(file))
+    }
+}
+
+fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+fn parse_property(def: &mut TileDef, property: &str, line_num: u32) -> Res<()> {
+    let unknown = || Error::UnknownProperty(line_num, property.into());
+    
+    let mut split = property.splitn(2, '=');
+    match (split.next().unwrap(), split.next()) {
+        ("solid", None) => def.solid = true,
+        ("one_way", None) => def.one_way = true,
+        ("breakable", None) => def.breakable = true,
+        ("texture", Some(value)) => {
+            def.texture = Some(try!(u32::from_str(value).map_err(|_| unknown())));
+        },
+        ("damage", Some(value)) => {
+            def.damage = try!(u32::from_str(value).map_err(|_| unknown()));
+        },
+        ("item", Some(value)) => {
+            def.item = try!(Item::parse(value).map_err(|_| unknown()));
+        },
+        _ => return Err(unknown()),
+    }
+    Ok(())
+}
+
+pub type Res<T> = Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    BadLine(u32),
+    UnknownProperty(u32, String),
+    UnknownTexture(u32, u32),
+    DuplicateSymbol(u32, char),
+    Io(io::Error),
+}
+
+impl Error {
+    pub fn line(&self) -> Option<u32> {
+        match *self {
+            Error::BadLine(line) |
+            Error::UnknownProperty(line, _) |
+            Error::UnknownTexture(line, _) |
+            Error::DuplicateSymbol(line, _) => Some(line),
+            Error::Io(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::BadLine(line) => write!(f, "{} bad tileset line", line),
+            Error::UnknownProperty(line, ref property) => {
+                write!(f, "{} unknown tile property '{}'", line, property)
+            },
+            Error::UnknownTexture(line, texture) => {
+                write!(f, "{} tileset has no texture {}", line, texture)
+            },
+            Error::DuplicateSymbol(line, symbol) => {
+                write!(f, "{} symbol '{}' is already used", line, symbol)
+            },
+            Error::Io(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::BadLine(_) => "bad tileset line",
+            Error::UnknownProperty(..) => "unknown tile property",
+            Error::UnknownTexture(..) => "unknown tileset texture",
+            Error::DuplicateSymbol(..) => "tile symbol is already used",
+            Error::Io(ref e) => error::Error::description(e),
+        }
+    }
+    
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(io: io::Error) -> Error {
+        Error::Io(io)
+    }
+}
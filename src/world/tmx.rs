@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::str::FromStr;
+use rustc_serialize::json::Json;
+use xml::common::Position;
+use xml::reader::{EventReader, XmlEvent};
+use world::tilemap::{Location, Error, Res};
+
+// Reads Tiled's XML formats (TMX maps and TSX tilesets) into the same shape
+// as its JSON exports, so there's only one importer.
+
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref value)| &value[..])
+    }
+    
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+    
+    fn children_named(&self, name: &str) -> Vec<&Element> {
+        self.children.iter().filter(|child| child.name == name).collect()
+    }
+}
+
+pub fn parse<R: Read>(reader: &mut R) -> Res<Json> {
+    let root = try!(read_tree(reader));
+    match &root.name[..] {
+        "map" => map_json(&root),
+        "tileset" => tileset_json(&root),
+        name => Err(Error::BadTiled(format!("unexpected <{}> document", name))),
+    }
+}
+
+// Tiled files are small, so the whole document is read into a tree first
+fn read_tree<R: Read>(reader: R) -> Res<Element> {
+    let mut events = EventReader::new(reader);
+    let mut open: Vec<Element> = Vec::new();
+    loop {
+        let event = try!(events.next().map_err(|e| xml_error(&e, e.msg())));
+        match event {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                open.push(Element {
+                    name: name.local_name,
+                    attributes: attributes.into_iter().map(|a| (a.name.local_name, a.value)).collect(),
+                    children: Vec::new(),
+                    text: String::new(),
+                });
+            },
+            XmlEvent::EndElement { .. } => {
+                let element = open.pop().unwrap();
+                match open.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            },
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(element) = open.last_mut() {
+                    element.text.push_str(&text);
+                }
+            },
+            XmlEvent::EndDocument => return Err(xml_error(&events, "no root element")),
+            _ => {}
+        }
+    }
+}
+
+fn xml_error<P: Position>(at: &P, reason: &str) -> Error {
+    let position = at.position();
+    Error::BadXml {
+        at: Location {
+            line: position.row as u32 + 1,
+            column: position.column as u32 + 1,
+        },
+        reason: reason.into(),
+    }
+}
+
+fn map_json(map: &Element) -> Res<Json> {
+    let mut json = BTreeMap::new();
+    for key in &["width", "height", "tilewidth", "tileheight"] {
+        json.insert(key.to_string(), try!(attr_number(map, key)));
+    }
+    if let Some(orientation) = map.attr("orientation") {
+        json.insert("orientation".into(), Json::String(orientation.into()));
+    }
+    json.insert("infinite".into(), Json::Boolean(map.attr("infinite") == Some("1")));
+    
+    let tilesets: Res<Vec<_>> = map.children_named("tileset").into_iter().map(tileset_json).collect();
+    json.insert("tilesets".into(), Json::Array(try!(tilesets)));
+    json.insert("layers".into(), Json::Array(try!(layers_json(map))));
+    json.insert("properties".into(), properties_json(map));
+    Ok(Json::Object(json))
+}
+
+fn tileset_json(set: &Element) -> Res<Json> {
+    let mut json = BTreeMap::new();
+    if let Some(first_gid) = set.attr("firstgid").and_then(number) {
+        json.insert("firstgid".into(), first_gid);
+    }
+    for key in &["source", "name"] {
+        if let Some(value) = set.attr(key) {
+            json.insert(key.to_string(), Json::String(value.into()));
+        }
+    }
+    
+    let mut tiles = Vec::new();
+    for tile in set.children_named("tile") {
+        let mut tile_json = BTreeMap::new();
+        tile_json.insert("id".into(), try!(attr_number(tile, "id")));
+        if let Some(kind) = tile.attr("type").or(tile.attr("class")) {
+            tile_json.insert("type".into(), Json::String(kind.into()));
+        }
+        if let Some(image) = tile.child("image").and_then(|image| image.attr("source")) {
+            tile_json.insert("image".into(), Json::String(image.into()));
+        }
+        tile_json.insert("properties".into(), properties_json(tile));
+        tiles.push(Json::Object(tile_json));
+    }
+    if !tiles.is_empty() {
+        json.insert("tiles".into(), Json::Array(tiles));
+    }
+    
+    Ok(Json::Object(json))
+}
+
+fn layers_json(parent: &Element) -> Res<Vec<Json>> {
+    let mut layers = Vec::new();
+    for child in &parent.children {
+        let mut json = BTreeMap::new();
+        json.insert("name".into(), Json::String(child.attr("name").unwrap_or("").into()));
+        json.insert("properties".into(), properties_json(child));
+        
+        match &child.name[..] {
+            "layer" => {
+                json.insert("type".into(), Json::String("tilelayer".into()));
+                json.insert("data".into(), try!(data_json(child)));
+            },
+            "objectgroup" => {
+                json.insert("type".into(), Json::String("objectgroup".into()));
+                let objects: Res<Vec<_>> = child.children_named("object").into_iter()
+                    .map(object_json)
+                    .collect();
+                json.insert("objects".into(), Json::Array(try!(objects)));
+            },
+            "group" => {
+                json.insert("type".into(), Json::String("group".into()));
+                json.insert("layers".into(), Json::Array(try!(layers_json(child))));
+            },
+            _ => continue,
+        }
+        layers.push(Json::Object(json));
+    }
+    Ok(layers)
+}
+
+fn data_json(layer: &Element) -> Res<Json> {
+    let data = try!(layer.child("data").ok_or_else(|| {
+        Error::BadTiled("tile layer has no data".into())
+    }));
+    
+    let gids: Res<Vec<_>> = match data.attr("encoding") {
+        Some("csv") => data.text.split(',').map(|gid| {
+            u64::from_str(gid.trim()).map(Json::U64).map_err(|_| {
+                Error::BadTiled(format!("{} isn't a tile", gid.trim()))
+            })
+        }).collect(),
+        None => Ok(data.children_named("tile").iter().map(|tile| {
+            Json::U64(tile.attr("gid").and_then(|gid| u64::from_str(gid).ok()).unwrap_or(0))
+        }).collect()),
+        Some(encoding) => return Err(Error::UnsupportedTiled(format!("{} layer data", encoding))),
+    };
+    Ok(Json::Array(try!(gids)))
+}
+
+fn object_json(object: &Element) -> Res<Json> {
+    let mut json = BTreeMap::new();
+    for key in &["x", "y"] {
+        json.insert(key.to_string(), try!(attr_number(object, key)));
+    }
+    for key in &["width", "height", "gid"] {
+        if let Some(value) = object.attr(key).and_then(number) {
+            json.insert(key.to_string(), value);
+        }
+    }
+    if let Some(kind) = object.attr("type").or(object.attr("class")) {
+        json.insert("type".into(), Json::String(kind.into()));
+    }
+    json.insert("properties".into(), properties_json(object));
+    Ok(Json::Object(json))
+}
+
+fn properties_json(element: &Element) -> Json {
+    let properties = element.child("properties").map(|properties| {
+        properties.children_named("property").iter().map(|property| {
+            let mut json = BTreeMap::new();
+            json.insert("name".into(), Json::String(property.attr("name").unwrap_or("").into()));
+            // Multi-line strings are kept in the element instead
+            let value = property.attr("value").unwrap_or(&property.text);
+            json.insert("value".into(), Json::String(value.into()));
+            Json::Object(json)
+        }).collect()
+    });
+    Json::Array(properties.unwrap_or(Vec::new()))
+}
+
+fn number(value: &str) -> Option<Json> {
+    u64::from_str(value).map(Json::U64).ok().or_else(|| {
+        f64::from_str(value).map(Json::F64).ok()
+    })
+}
+
+fn attr_number(element: &Element, key: &str) -> Res<Json> {
+    element.attr(key).and_then(number).ok_or_else(|| {
+        Error::BadTiled(format!("<{}> needs a numeric {}", element.name, key))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json::Json;
+    use super::parse;
+    use world::tilemap::Error;
+    
+    #[test]
+    fn xml_features() {
+        let tmx = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                   <!-- Written by hand -->\n\
+                   <map width=\"2\" height=\"1\" tilewidth=\"32\" tileheight=\"32\">\n\
+                   <properties>\n\
+                   <property name=\"name\" value=\"Fish &amp; &quot;chips&quot;\"/>\n\
+                   <property name=\"notes\"><![CDATA[<b>two</b>\nlines]]></property>\n\
+                   </properties>\n\
+                   <layer name=\"main\"><data encoding=\"csv\">\n1,<!-- gap -->0\n</data></layer>\n\
+                   </map>\n";
+        let map = parse(&mut tmx.as_bytes()).unwrap();
+        
+        let properties = map.find("properties").and_then(Json::as_array).unwrap();
+        let value = |i: usize| properties[i].find("value").and_then(Json::as_string);
+        assert_eq!(value(0), Some("Fish & \"chips\""));
+        assert_eq!(value(1), Some("<b>two</b>\nlines"));
+        
+        let layers = map.find("layers").and_then(Json::as_array).unwrap();
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].find("data"), Some(&Json::Array(vec![Json::U64(1), Json::U64(0)])));
+    }
+    
+    #[test]
+    fn bad_xml() {
+        match parse(&mut &b"<map width=\"2\">\n<layer>\n</map>\n"[..]) {
+            Err(Error::BadXml { at, .. }) => assert_eq!(at.line, 3),
+            other => panic!("expected bad XML, got {:?}", other),
+        }
+        match parse(&mut &b"<tile id=\"0\"/>"[..]) {
+            Err(Error::BadTiled(_)) => {},
+            other => panic!("expected an unexpected document, got {:?}", other),
+        }
+    }
+}